@@ -1,35 +1,248 @@
-use bevy::prelude::*;
+use std::time::Duration;
 
-#[derive(Component, Clone, Default)]
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::{TypePath, TypeUuid},
+    utils::{BoxedFuture, HashMap},
+};
+use serde::Deserialize;
+
+/// Something that should happen when a particular frame comes up, like a
+/// foot hitting the ground.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum AnimationCue {
+    Footstep,
+}
+
+/// Sent when a frame with an [`AnimationCue`] on it is shown.
+#[derive(Event)]
+pub(crate) struct AnimationEvent {
+    pub(crate) entity: Entity,
+    pub(crate) cue: AnimationCue,
+}
+
+#[derive(Component, Clone, Debug)]
 pub(crate) struct AnimationIndices {
     pub(crate) first: usize,
     pub(crate) last: usize,
     pub(crate) timer: Timer,
+    /// How long each frame from `first` to `last` is shown. Empty shows them
+    /// all for the timer's duration.
+    pub(crate) frame_durations: Vec<Duration>,
+    /// Plays faster above 1 and slower below.
+    pub(crate) speed: f32,
+    /// Atlas frames that send an [`AnimationEvent`] when they're shown.
+    pub(crate) cues: Vec<(usize, AnimationCue)>,
+}
+
+impl Default for AnimationIndices {
+    fn default() -> Self {
+        AnimationIndices {
+            first: 0,
+            last: 0,
+            timer: Timer::default(),
+            frame_durations: Vec::new(),
+            speed: 1.0,
+            cues: Vec::new(),
+        }
+    }
+}
+
+impl AnimationIndices {
+    pub(crate) fn with_cue_on_every_frame(mut self, cue: AnimationCue) -> Self {
+        self.cues = (self.first..=self.last).map(|frame| (frame, cue)).collect();
+        self
+    }
+
+    /// A clip that plays once has shown its last frame for long enough.
+    /// Looping clips never finish.
+    pub(crate) fn finished(&self, sprite: &TextureAtlasSprite) -> bool {
+        self.timer.mode() == TimerMode::Once
+            && sprite.index == self.last
+            && self.timer.finished()
+    }
+
+    /// Swaps in `clip` from its first frame. Returns the cue on that frame, if
+    /// there is one.
+    pub(crate) fn play(
+        &mut self,
+        sprite: &mut TextureAtlasSprite,
+        clip: &AnimationIndices,
+    ) -> Option<AnimationCue> {
+        *self = clip.clone();
+        self.show_frame(sprite, clip.first)
+    }
+
+    fn show_frame(
+        &mut self,
+        sprite: &mut TextureAtlasSprite,
+        index: usize,
+    ) -> Option<AnimationCue> {
+        sprite.index = index;
+        if let Some(duration) = self.frame_durations.get(index - self.first) {
+            self.timer.set_duration(*duration);
+        }
+        // A finished one-shot timer stays finished, so start each frame's
+        // time afresh. Looping ones carry the leftover over by themselves.
+        if self.timer.mode() == TimerMode::Once {
+            self.timer.reset();
+        }
+        self.cues
+            .iter()
+            .find(|(frame, _)| *frame == index)
+            .map(|(_, cue)| *cue)
+    }
 }
 
 pub(crate) fn animate(
     time: Res<Time>,
-    mut animated_sprites: Query<(&mut AnimationIndices, &mut TextureAtlasSprite)>,
+    mut animated_sprites: Query<(Entity, &mut AnimationIndices, &mut TextureAtlasSprite)>,
+    mut events: EventWriter<AnimationEvent>,
 ) {
-    for (mut indices, mut sprite) in &mut animated_sprites {
-        indices.timer.tick(time.delta());
-        if indices.timer.finished() {
-            let new_index = sprite.index + 1;
-            if new_index > indices.last {
-                if indices.timer.mode() == TimerMode::Repeating {
-                    sprite.index = indices.first;
-                }
+    for (entity, mut indices, mut sprite) in &mut animated_sprites {
+        let speed = indices.speed;
+        indices.timer.tick(time.delta().mul_f32(speed));
+        if !indices.timer.just_finished() {
+            continue;
+        }
+        let new_index = sprite.index + 1;
+        let shown = if new_index < indices.first {
+            // Not on this clip's frames at all, start from the top.
+            let first = indices.first;
+            indices.show_frame(&mut sprite, first)
+        } else if new_index > indices.last {
+            if indices.timer.mode() == TimerMode::Repeating {
+                let first = indices.first;
+                indices.show_frame(&mut sprite, first)
             } else {
-                sprite.index = new_index;
+                None
             }
+        } else {
+            indices.show_frame(&mut sprite, new_index)
+        };
+        if let Some(cue) = shown {
+            events.send(AnimationEvent { entity, cue });
         }
     }
 }
 
-/// Makes wild assumptions about the identities of animations to decide if the
-/// current playing one should be replaced by the source one.
-pub(crate) fn maybe_change_animation(target: &mut AnimationIndices, source: &AnimationIndices) {
-    if target.first != source.first {
-        *target = source.clone();
+/// The tagged animations from an Aseprite sprite sheet, loaded from the JSON
+/// Aseprite writes next to the sheet. Export with
+/// `aseprite -b player.ase --sheet player.png --data player.aseprite.json
+/// --format json-array --list-tags` and keep the sheet a single row.
+#[derive(TypeUuid, TypePath, Debug)]
+#[uuid = "b7e0c4a2-93d1-4f55-8c6e-2a7f1d9e0b34"]
+pub(crate) struct AnimationSheet {
+    pub(crate) clips: HashMap<String, AnimationIndices>,
+}
+
+/// Just the parts of Aseprite's JSON export we use.
+#[derive(Deserialize)]
+struct AsepriteJson {
+    frames: Vec<AsepriteFrame>,
+    meta: AsepriteMeta,
+}
+
+#[derive(Deserialize)]
+struct AsepriteFrame {
+    /// Milliseconds.
+    duration: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AsepriteMeta {
+    #[serde(default)]
+    frame_tags: Vec<AsepriteTag>,
+}
+
+#[derive(Deserialize)]
+struct AsepriteTag {
+    name: String,
+    from: usize,
+    to: usize,
+    #[serde(default)]
+    direction: String,
+    /// Aseprite writes this as a string, and leaves it out for tags that loop
+    /// forever.
+    #[serde(default)]
+    repeat: Option<String>,
+}
+
+impl AnimationSheet {
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<AnimationSheet, String> {
+        let json: AsepriteJson = serde_json::from_slice(bytes).map_err(|e| e.to_string())?;
+        let mut clips = HashMap::new();
+        for tag in json.meta.frame_tags {
+            if tag.from > tag.to || tag.to >= json.frames.len() {
+                return Err(format!(
+                    "Tag {} covers frames {}..={} but there are only {}",
+                    tag.name,
+                    tag.from,
+                    tag.to,
+                    json.frames.len()
+                ));
+            }
+            if !tag.direction.is_empty() && tag.direction != "forward" {
+                warn!(
+                    "Tag {} plays {}, only forward is supported",
+                    tag.name, tag.direction
+                );
+            }
+            let frame_durations: Vec<_> = json.frames[tag.from..=tag.to]
+                .iter()
+                .map(|frame| Duration::from_millis(frame.duration))
+                .collect();
+            let mode = match tag.repeat.as_deref() {
+                None | Some("0") => TimerMode::Repeating,
+                Some(_) => TimerMode::Once,
+            };
+            let clip = AnimationIndices {
+                first: tag.from,
+                last: tag.to,
+                timer: Timer::new(frame_durations[0], mode),
+                frame_durations,
+                ..default()
+            };
+            if clips.insert(tag.name.clone(), clip).is_some() {
+                return Err(format!("Tag {} is there twice", tag.name));
+            }
+        }
+        Ok(AnimationSheet { clips })
+    }
+
+    pub(crate) fn clip(&self, name: &str) -> Result<AnimationIndices, String> {
+        self.clips
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("No {name} tag"))
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct AnimationSheetLoader;
+
+impl AssetLoader for AnimationSheetLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let sheet = AnimationSheet::from_bytes(bytes).map_err(|e| {
+                bevy::asset::Error::msg(format!(
+                    "Bad animations {}: {}",
+                    load_context.path().display(),
+                    e
+                ))
+            })?;
+            load_context.set_default_asset(LoadedAsset::new(sheet));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["aseprite.json"]
     }
 }
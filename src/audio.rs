@@ -0,0 +1,123 @@
+use bevy::audio::{PlaybackMode, Volume};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use rand::prelude::*;
+
+use crate::settings::Settings;
+use crate::{Randomizer, LEFT_WALL, RIGHT_WALL};
+
+/// Copies of the same sound that can play at once. Any more are dropped, so
+/// a dozen rocks breaking together doesn't clip.
+const MAX_VOICES_PER_SOUND: usize = 3;
+/// Each play is sped up or slowed down by up to this fraction.
+const PITCH_VARIATION: f32 = 0.08;
+/// Each play is quieter by up to this fraction.
+const VOLUME_VARIATION: f32 = 0.15;
+/// Distance between the listener's ears, in the same made up units as
+/// [`PAN_WIDTH`].
+const EAR_GAP: f32 = 2.0;
+/// How far off center a sound at a wall is put. Less than half the ear gap,
+/// so the far side is quieter but never silent.
+const PAN_WIDTH: f32 = 0.5;
+
+/// Plays sound effects, panned left and right by where they happened unless
+/// [`Settings`] asks for mono. The music is [`crate::music::MusicPlugin`]'s.
+///
+/// Requires [`Settings`] and [`Randomizer`].
+/// Anything can ask for a sound by sending a [`PlaySound`]; without this plugin
+/// those are just dropped.
+pub(crate) struct AudioFxPlugin;
+
+impl Plugin for AudioFxPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PlaySound>()
+            // Not tied to a state, the explosion is requested on the way out of
+            // Playing.
+            .add_systems(Update, play_audio);
+    }
+}
+
+/// Asks for a one shot sound effect.
+#[derive(Event)]
+pub(crate) struct PlaySound {
+    pub(crate) source: Handle<AudioSource>,
+    /// Where it happened, -1 at the left wall to 1 at the right. `None` plays
+    /// it in the middle.
+    pub(crate) pan: Option<f32>,
+}
+
+impl PlaySound {
+    pub(crate) fn new(source: Handle<AudioSource>) -> Self {
+        PlaySound { source, pan: None }
+    }
+
+    /// A sound that came from `x` in the arena, panned towards that side.
+    pub(crate) fn at(source: Handle<AudioSource>, x: f32) -> Self {
+        let pan = (x - LEFT_WALL) / (RIGHT_WALL - LEFT_WALL) * 2.0 - 1.0;
+        PlaySound {
+            source,
+            pan: Some(pan.clamp(-1.0, 1.0)),
+        }
+    }
+}
+
+/// A sound effect that's still playing. Bevy despawns it once the sink runs
+/// dry.
+#[derive(Component)]
+struct Sfx {
+    source: Handle<AudioSource>,
+}
+
+fn play_audio(
+    settings: Res<Settings>,
+    mut rng: ResMut<Randomizer>,
+    mut sounds: EventReader<PlaySound>,
+    playing: Query<&Sfx>,
+    mut commands: Commands,
+) {
+    let mut voices = HashMap::<Handle<AudioSource>, usize>::new();
+    for sfx in &playing {
+        *voices.entry(sfx.source.clone()).or_default() += 1;
+    }
+
+    for sound in &mut sounds {
+        let count = voices.entry(sound.source.clone()).or_default();
+        if *count >= MAX_VOICES_PER_SOUND {
+            continue;
+        }
+        *count += 1;
+
+        let speed = 1.0 + rng.sfx.gen_range(-PITCH_VARIATION..=PITCH_VARIATION);
+        let volume = settings.sfx_volume * (1.0 - rng.sfx.gen_range(0.0..=VOLUME_VARIATION));
+        let playback = PlaybackSettings {
+            mode: PlaybackMode::Despawn,
+            volume: Volume::new_relative(volume),
+            speed,
+            ..default()
+        };
+        let sfx = Sfx {
+            source: sound.source.clone(),
+        };
+        match sound.pan.filter(|_| !settings.mono) {
+            Some(pan) => commands.spawn((
+                SpatialAudioBundle {
+                    source: sound.source.clone(),
+                    settings: playback,
+                    spatial: SpatialSettings::new(
+                        Transform::IDENTITY,
+                        EAR_GAP,
+                        Vec3::X * pan * PAN_WIDTH,
+                    ),
+                },
+                sfx,
+            )),
+            None => commands.spawn((
+                AudioBundle {
+                    source: sound.source.clone(),
+                    settings: playback,
+                },
+                sfx,
+            )),
+        };
+    }
+}
@@ -0,0 +1,112 @@
+use bevy::{asset::LoadState, prelude::*};
+use iyes_progress::prelude::*;
+
+use crate::gameplay::Ball;
+use crate::level::{Goal, Level, CAMPAIGN};
+use crate::GameState;
+
+/// Which level of the [`CAMPAIGN`] gets built the next time we start playing.
+#[derive(Resource, Default)]
+pub(crate) struct Campaign {
+    pub(crate) level: usize,
+    /// The [`CAMPAIGN`] in order, filled in by [`load_levels`].
+    pub(crate) levels: Vec<Handle<Level>>,
+}
+
+impl Campaign {
+    pub(crate) fn starting_at(level: usize) -> Self {
+        Campaign {
+            level,
+            levels: Vec::new(),
+        }
+    }
+
+    pub(crate) fn current<'a>(&self, levels: &'a Assets<Level>) -> Option<&'a Level> {
+        self.levels
+            .get(self.level)
+            .and_then(|handle| levels.get(handle))
+    }
+
+    pub(crate) fn is_last_level(&self) -> bool {
+        self.level + 1 >= self.levels.len()
+    }
+}
+
+pub(crate) fn load_levels(asset_server: Res<AssetServer>, mut campaign: ResMut<Campaign>) {
+    campaign.levels = CAMPAIGN.iter().map(|path| asset_server.load(*path)).collect();
+}
+
+/// Holds the loading screen until every level is in.
+pub(crate) fn track_levels(asset_server: Res<AssetServer>, campaign: Res<Campaign>) -> Progress {
+    let done = campaign
+        .levels
+        .iter()
+        .filter(|handle| asset_server.get_load_state(*handle) == LoadState::Loaded)
+        .count();
+    Progress {
+        done: done as u32,
+        total: CAMPAIGN.len() as u32,
+    }
+}
+
+/// How far the current level is towards its [`Goal`].
+#[derive(Resource, Default)]
+pub(crate) struct LevelProgress {
+    pub(crate) goal: Goal,
+    pub(crate) elapsed: f32,
+    /// Bombs the paddle has left to throw, if the level limits them.
+    pub(crate) bombs_left: Option<u32>,
+}
+
+impl LevelProgress {
+    pub(crate) fn new(goal: Goal) -> Self {
+        LevelProgress {
+            goal,
+            elapsed: 0.0,
+            bombs_left: match goal {
+                Goal::OutlastBombs(bombs) => Some(bombs),
+                Goal::Survive(_) | Goal::Endless => None,
+            },
+        }
+    }
+
+    /// Uses up one of the paddle's bombs. Returns false when it's out.
+    pub(crate) fn take_bomb(&mut self) -> bool {
+        match &mut self.bombs_left {
+            Some(0) => false,
+            Some(left) => {
+                *left -= 1;
+                true
+            }
+            None => true,
+        }
+    }
+}
+
+pub(crate) fn check_for_level_complete(
+    time: Res<Time>,
+    mut progress: ResMut<LevelProgress>,
+    mut next_state: ResMut<NextState<GameState>>,
+    balls: Query<(), With<Ball>>,
+) {
+    progress.elapsed += time.delta_seconds();
+
+    let complete = match progress.goal {
+        Goal::Survive(seconds) => progress.elapsed >= seconds,
+        Goal::OutlastBombs(_) => progress.bombs_left == Some(0) && balls.is_empty(),
+        Goal::Endless => false,
+    };
+
+    if complete {
+        next_state.set(GameState::LevelComplete);
+    }
+}
+
+/// Moves on to the next level, or back to the first once they're all done.
+pub(crate) fn advance_campaign(mut campaign: ResMut<Campaign>) {
+    campaign.level = if campaign.is_last_level() {
+        0
+    } else {
+        campaign.level + 1
+    };
+}
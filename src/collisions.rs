@@ -0,0 +1,90 @@
+use bevy::prelude::*;
+use bevy_xpbd_2d::prelude::*;
+
+use crate::gameplay::{Ball, Rock, Wall};
+use crate::paddle::Paddle;
+use crate::player::Player;
+
+/// A ball started touching a rock.
+///
+/// Every `position` here is where the ball was, which is close enough to
+/// where it hit.
+#[derive(Event, Clone, Copy, Debug)]
+pub(crate) struct BallHitRock {
+    pub(crate) rock: Entity,
+    pub(crate) position: Vec2,
+}
+
+/// A ball started touching one of the arena's walls.
+#[derive(Event, Clone, Copy, Debug)]
+pub(crate) struct BallHitWall {
+    pub(crate) ball: Entity,
+    pub(crate) position: Vec2,
+    /// The top wall behind the paddle, which swallows balls instead of
+    /// bouncing them.
+    pub(crate) ball_destroyer: bool,
+}
+
+/// A ball started touching the player.
+#[derive(Event, Clone, Copy, Debug)]
+pub(crate) struct BallHitPlayer {
+    pub(crate) position: Vec2,
+}
+
+/// A ball started touching the paddle.
+#[derive(Event, Clone, Copy, Debug)]
+pub(crate) struct BallHitPaddle {
+    pub(crate) position: Vec2,
+}
+
+/// Turns xpbd's raw contacts into the typed events above. `CollisionStarted`
+/// only fires on the first frame two things touch, so each contact is
+/// reported exactly once, when it happens rather than when the ball leaves.
+pub(crate) fn emit_ball_hits(
+    mut started: EventReader<CollisionStarted>,
+    balls: Query<&Transform, With<Ball>>,
+    targets: Query<(
+        Option<&Rock>,
+        Option<&Wall>,
+        Option<&Player>,
+        Option<&Paddle>,
+    )>,
+    mut rock_hits: EventWriter<BallHitRock>,
+    mut wall_hits: EventWriter<BallHitWall>,
+    mut player_hits: EventWriter<BallHitPlayer>,
+    mut paddle_hits: EventWriter<BallHitPaddle>,
+) {
+    for CollisionStarted(a, b) in &mut started {
+        let (ball, target, ball_transform) = if let Ok(transform) = balls.get(*a) {
+            (*a, *b, transform)
+        } else if let Ok(transform) = balls.get(*b) {
+            (*b, *a, transform)
+        } else {
+            continue;
+        };
+        let position = ball_transform.translation.truncate();
+        let Ok((rock, wall, player, paddle)) = targets.get(target) else {
+            continue;
+        };
+
+        if rock.is_some() {
+            rock_hits.send(BallHitRock {
+                rock: target,
+                position,
+            });
+        }
+        if let Some(wall) = wall {
+            wall_hits.send(BallHitWall {
+                ball,
+                position,
+                ball_destroyer: wall.ball_destroyer,
+            });
+        }
+        if player.is_some() {
+            player_hits.send(BallHitPlayer { position });
+        }
+        if paddle.is_some() {
+            paddle_hits.send(BallHitPaddle { position });
+        }
+    }
+}
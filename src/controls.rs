@@ -0,0 +1,367 @@
+use bevy::prelude::*;
+use leafwing_input_manager::{
+    axislike::{SingleAxis, VirtualAxis},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::player::Player;
+use crate::settings::SettingsState;
+use crate::ui::{self, MenuInput};
+use crate::{storage, Action, GameMode, LDAssets};
+
+const STORAGE_KEY: &str = "controls";
+/// Left stick tilt that still counts as standing still.
+const STICK_DEADZONE: f32 = 0.15;
+
+/// One thing the player can bind.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Control {
+    Left,
+    Right,
+    Jump,
+}
+
+impl Control {
+    const ALL: [Control; 3] = [Control::Left, Control::Right, Control::Jump];
+
+    fn label(self) -> &'static str {
+        match self {
+            Control::Left => "Move left",
+            Control::Right => "Move right",
+            Control::Jump => "Jump",
+        }
+    }
+}
+
+/// A binding for every [`Control`], all from the same kind of device.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub(crate) struct ControlSet<T> {
+    pub(crate) left: T,
+    pub(crate) right: T,
+    pub(crate) jump: T,
+}
+
+impl<T: Copy + PartialEq> ControlSet<T> {
+    pub(crate) fn get(&self, control: Control) -> T {
+        match control {
+            Control::Left => self.left,
+            Control::Right => self.right,
+            Control::Jump => self.jump,
+        }
+    }
+
+    /// Binds `input` to `control`, unless some other control already has it.
+    /// Returns that other control on a conflict.
+    pub(crate) fn bind(&mut self, control: Control, input: T) -> Result<(), Control> {
+        if let Some(other) = Control::ALL
+            .into_iter()
+            .find(|&other| other != control && self.get(other) == input)
+        {
+            return Err(other);
+        }
+        match control {
+            Control::Left => self.left = input,
+            Control::Right => self.right = input,
+            Control::Jump => self.jump = input,
+        }
+        Ok(())
+    }
+}
+
+/// The player's key and gamepad bindings. Saved whenever the controls screen
+/// closes.
+#[derive(Resource, Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub(crate) struct Controls {
+    pub(crate) keys: ControlSet<KeyCode>,
+    pub(crate) buttons: ControlSet<GamepadButtonType>,
+}
+
+impl Default for Controls {
+    fn default() -> Self {
+        Controls {
+            keys: ControlSet {
+                left: KeyCode::Left,
+                right: KeyCode::Right,
+                jump: KeyCode::Up,
+            },
+            buttons: ControlSet {
+                left: GamepadButtonType::DPadLeft,
+                right: GamepadButtonType::DPadRight,
+                jump: GamepadButtonType::South,
+            },
+        }
+    }
+}
+
+impl Controls {
+    /// Reads the saved bindings. Missing or broken ones fall back to the
+    /// defaults.
+    pub(crate) fn load() -> Self {
+        storage::load_ron(STORAGE_KEY, "controls")
+    }
+
+    /// The rebindable controls plus the left stick and space, and in solo
+    /// games A/D and W as well. Versus leaves those to the paddle's side of the
+    /// keyboard. Extra keys the player has since rebound to something else are
+    /// left out.
+    pub(crate) fn input_map(&self, mode: GameMode) -> InputMap<Action> {
+        let mut input_map = InputMap::default();
+        input_map.insert(
+            UserInput::VirtualAxis(VirtualAxis {
+                negative: self.keys.left.into(),
+                positive: self.keys.right.into(),
+            }),
+            Action::Move,
+        );
+        input_map.insert(
+            UserInput::VirtualAxis(VirtualAxis {
+                negative: self.buttons.left.into(),
+                positive: self.buttons.right.into(),
+            }),
+            Action::Move,
+        );
+        input_map.insert(self.keys.jump, Action::Jump);
+        input_map.insert(self.buttons.jump, Action::Jump);
+
+        input_map.insert(
+            SingleAxis::symmetric(GamepadAxisType::LeftStickX, STICK_DEADZONE),
+            Action::Move,
+        );
+        let is_free = |key| {
+            Control::ALL
+                .iter()
+                .all(|&control| self.keys.get(control) != key)
+        };
+        if mode == GameMode::Solo {
+            if is_free(KeyCode::A) && is_free(KeyCode::D) {
+                input_map.insert(
+                    UserInput::VirtualAxis(VirtualAxis {
+                        negative: KeyCode::A.into(),
+                        positive: KeyCode::D.into(),
+                    }),
+                    Action::Move,
+                );
+            }
+            if is_free(KeyCode::W) {
+                input_map.insert(KeyCode::W, Action::Jump);
+            }
+        }
+        if is_free(KeyCode::Space) {
+            input_map.insert(KeyCode::Space, Action::Jump);
+        }
+        input_map
+    }
+}
+
+/// The controls screen, opened from the settings screen.
+///
+/// Requires [`LDAssets`], [`Controls`], [`GameMode`] and the settings plugin.
+pub(crate) struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(SettingsState::Controls), controls_menu_setup)
+            .add_systems(
+                OnExit(SettingsState::Controls),
+                (save_controls, ui::despawn_all::<ControlsMenu>),
+            )
+            .add_systems(
+                Update,
+                (navigate_controls_menu, update_controls_menu)
+                    .chain()
+                    .run_if(in_state(SettingsState::Controls)),
+            )
+            .add_systems(
+                Update,
+                apply_controls.run_if(resource_changed::<Controls>()),
+            );
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ControlsRow {
+    Bind(Control),
+    Reset,
+    Back,
+}
+
+const ROWS: [ControlsRow; 5] = [
+    ControlsRow::Bind(Control::Left),
+    ControlsRow::Bind(Control::Right),
+    ControlsRow::Bind(Control::Jump),
+    ControlsRow::Reset,
+    ControlsRow::Back,
+];
+
+#[derive(Resource, Default)]
+struct ControlsScreen {
+    selected: usize,
+    /// Waiting for the next key or button to bind to the selected row.
+    listening: bool,
+    /// Why the last binding didn't take, or what to press.
+    message: String,
+}
+
+#[derive(Component)]
+struct ControlsMenu;
+
+#[derive(Component)]
+struct ControlsRowText(usize);
+
+#[derive(Component)]
+struct ControlsMessage;
+
+fn row_text(row: ControlsRow, controls: &Controls) -> String {
+    match row {
+        ControlsRow::Bind(control) => format!(
+            "{:<11} {:?} / {:?}",
+            control.label(),
+            controls.keys.get(control),
+            controls.buttons.get(control)
+        ),
+        ControlsRow::Reset => "Reset to defaults".to_owned(),
+        ControlsRow::Back => "Back".to_owned(),
+    }
+}
+
+fn controls_menu_setup(assets: Res<LDAssets>, controls: Res<Controls>, mut commands: Commands) {
+    commands.insert_resource(ControlsScreen::default());
+    let style = |font_size, color| TextStyle {
+        font: assets.font.clone(),
+        font_size,
+        color,
+    };
+    commands
+        .spawn((ui::menu_overlay(0.8, ZIndex::Global(1)), ControlsMenu))
+        .with_children(|menu| {
+            menu.spawn(TextBundle::from_section(
+                "Controls",
+                style(60.0, Color::WHITE),
+            ));
+            for (i, row) in ROWS.into_iter().enumerate() {
+                menu.spawn((
+                    TextBundle::from_section(row_text(row, &controls), style(30.0, Color::GRAY)),
+                    ControlsRowText(i),
+                ));
+            }
+            menu.spawn((
+                TextBundle::from_section("", style(20.0, Color::WHITE)),
+                ControlsMessage,
+            ));
+        });
+}
+
+fn save_controls(controls: Res<Controls>) {
+    storage::save_ron(STORAGE_KEY, &*controls, "controls");
+}
+
+/// Up and down pick a row, space or enter starts listening on it. The next
+/// key binds the keyboard side, the next gamepad button the gamepad side.
+/// Escape cancels listening, or goes back to the settings screen. Tapping a
+/// row picks it, and tapping anywhere cancels listening, since there's no key
+/// to bind on a phone.
+fn navigate_controls_menu(
+    input: MenuInput,
+    rows: Query<(&ControlsRowText, &Node, &GlobalTransform)>,
+    mut screen: ResMut<ControlsScreen>,
+    mut controls: ResMut<Controls>,
+    mut next_state: ResMut<NextState<SettingsState>>,
+) {
+    if screen.listening {
+        let ControlsRow::Bind(control) = ROWS[screen.selected] else {
+            screen.listening = false;
+            return;
+        };
+        let result = if input.keys.just_pressed(KeyCode::Escape) || input.any_tap() {
+            screen.listening = false;
+            screen.message.clear();
+            return;
+        } else if let Some(&key) = input.keys.get_just_pressed().next() {
+            controls.keys.bind(control, key)
+        } else if let Some(button) = input.buttons.get_just_pressed().next() {
+            controls.buttons.bind(control, button.button_type)
+        } else {
+            return;
+        };
+        screen.listening = false;
+        screen.message = match result {
+            Ok(()) => String::new(),
+            Err(other) => format!("Already used for {}", other.label()),
+        };
+        return;
+    }
+
+    if input.pressed(KeyCode::Escape, GamepadButtonType::East) {
+        next_state.set(SettingsState::Open);
+        return;
+    }
+    if input.pressed(KeyCode::Up, GamepadButtonType::DPadUp) {
+        screen.selected = screen.selected.saturating_sub(1);
+    }
+    if input.pressed(KeyCode::Down, GamepadButtonType::DPadDown) {
+        screen.selected = (screen.selected + 1).min(ROWS.len() - 1);
+    }
+    let tapped = rows
+        .iter()
+        .find(|(_, node, transform)| input.tap_on(node, transform).is_some())
+        .map(|(row, _, _)| row.0);
+    if let Some(index) = tapped {
+        screen.selected = index;
+    }
+    if input.confirmed() || tapped.is_some() {
+        match ROWS[screen.selected] {
+            ControlsRow::Bind(control) => {
+                screen.listening = true;
+                screen.message = format!(
+                    "Press a key or button for {} (Escape cancels)",
+                    control.label()
+                );
+            }
+            ControlsRow::Reset => {
+                *controls = Controls::default();
+                screen.message.clear();
+            }
+            ControlsRow::Back => next_state.set(SettingsState::Open),
+        }
+    }
+}
+
+fn update_controls_menu(
+    screen: Res<ControlsScreen>,
+    controls: Res<Controls>,
+    mut rows: Query<(&ControlsRowText, &mut Text), Without<ControlsMessage>>,
+    mut message: Query<&mut Text, With<ControlsMessage>>,
+) {
+    if !screen.is_changed() && !controls.is_changed() {
+        return;
+    }
+    for (row, mut text) in &mut rows {
+        text.sections[0].value = row_text(ROWS[row.0], &controls);
+        text.sections[0].style.color = match (row.0 == screen.selected, screen.listening) {
+            (true, true) => Color::RED,
+            (true, false) => Color::YELLOW,
+            (false, _) => Color::GRAY,
+        };
+    }
+    for mut text in &mut message {
+        text.sections[0].value = screen.message.clone();
+    }
+}
+
+/// Rebinding from the pause menu takes effect straight away. A player
+/// that's only listening to one gamepad keeps listening to it.
+fn apply_controls(
+    controls: Res<Controls>,
+    mode: Res<GameMode>,
+    mut players: Query<&mut InputMap<Action>, With<Player>>,
+) {
+    for mut input_map in &mut players {
+        let gamepad = input_map.gamepad();
+        *input_map = controls.input_map(*mode);
+        if let Some(gamepad) = gamepad {
+            input_map.set_gamepad(gamepad);
+        }
+    }
+}
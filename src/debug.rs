@@ -0,0 +1,48 @@
+use bevy::prelude::*;
+use bevy_xpbd_2d::prelude::*;
+
+use crate::paddle::PaddlePrediction;
+use crate::settings::Settings;
+use crate::GameState;
+
+/// Draws colliders, contacts and where the paddle thinks the balls are going
+/// when the debug setting is on.
+///
+/// Requires [`Settings`], [`GameState`] and the physics plugins built with
+/// their debug renderer.
+pub(crate) struct DebugPlugin;
+
+impl Plugin for DebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            configure_physics_debug.run_if(resource_changed::<Settings>()),
+        )
+        .add_systems(
+            Update,
+            draw_paddle_prediction.run_if(
+                in_state(GameState::Playing).and_then(|settings: Res<Settings>| settings.debug),
+            ),
+        );
+    }
+}
+
+fn configure_physics_debug(settings: Res<Settings>, mut commands: Commands) {
+    commands.insert_resource(if settings.debug {
+        PhysicsDebugConfig::all()
+    } else {
+        PhysicsDebugConfig::none()
+    });
+}
+
+fn draw_paddle_prediction(prediction: Option<Res<PaddlePrediction>>, mut gizmos: Gizmos) {
+    let Some(prediction) = prediction else {
+        return;
+    };
+    for (i, interception) in prediction.interceptions.iter().enumerate() {
+        // The one the paddle is going for stands out.
+        let color = if i == 0 { Color::YELLOW } else { Color::GRAY };
+        gizmos.linestrip_2d(interception.path.iter().copied(), color);
+        gizmos.circle_2d(interception.point, 6.0, color);
+    }
+}
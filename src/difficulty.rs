@@ -0,0 +1,95 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::PADDLE_SPEED;
+
+/// How hard the paddle tries. Picked with the `difficulty` option or on the
+/// splash screen.
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub(crate) enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+    Cruel,
+}
+
+/// The knobs a [`Difficulty`] turns.
+pub(crate) struct DifficultyProfile {
+    pub(crate) paddle_speed: f32,
+    /// Seconds the paddle sits still after picking a new ball to chase.
+    pub(crate) reaction_delay: f32,
+    /// The paddle aims up to this many pixels off where the ball will
+    /// actually come up.
+    pub(crate) prediction_error: f32,
+    /// Multiplies the level's time between bombs.
+    pub(crate) spawn_interval_scale: f32,
+}
+
+impl Difficulty {
+    pub(crate) const ALL: [Difficulty; 4] = [
+        Difficulty::Easy,
+        Difficulty::Normal,
+        Difficulty::Hard,
+        Difficulty::Cruel,
+    ];
+
+    pub(crate) fn profile(self) -> DifficultyProfile {
+        match self {
+            Difficulty::Easy => DifficultyProfile {
+                paddle_speed: PADDLE_SPEED * 0.6,
+                reaction_delay: 0.6,
+                prediction_error: 48.0,
+                spawn_interval_scale: 1.5,
+            },
+            Difficulty::Normal => DifficultyProfile {
+                paddle_speed: PADDLE_SPEED,
+                reaction_delay: 0.25,
+                prediction_error: 16.0,
+                spawn_interval_scale: 1.0,
+            },
+            Difficulty::Hard => DifficultyProfile {
+                paddle_speed: PADDLE_SPEED * 1.4,
+                reaction_delay: 0.1,
+                prediction_error: 4.0,
+                spawn_interval_scale: 0.75,
+            },
+            Difficulty::Cruel => DifficultyProfile {
+                paddle_speed: PADDLE_SPEED * 2.0,
+                reaction_delay: 0.0,
+                prediction_error: 0.0,
+                spawn_interval_scale: 0.5,
+            },
+        }
+    }
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+            Difficulty::Cruel => "Cruel",
+        }
+    }
+
+    /// Case insensitive, so `difficulty=hard` works.
+    pub(crate) fn from_name(name: &str) -> Option<Difficulty> {
+        Difficulty::ALL
+            .into_iter()
+            .find(|difficulty| difficulty.name().eq_ignore_ascii_case(name))
+    }
+
+    /// The next one up or down the list, stopping at the ends.
+    pub(crate) fn step(self, harder: bool) -> Difficulty {
+        let index = Difficulty::ALL
+            .iter()
+            .position(|&difficulty| difficulty == self)
+            .unwrap_or_default();
+        let index = if harder {
+            (index + 1).min(Difficulty::ALL.len() - 1)
+        } else {
+            index.saturating_sub(1)
+        };
+        Difficulty::ALL[index]
+    }
+}
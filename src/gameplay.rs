@@ -0,0 +1,831 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, sprite::Anchor, utils::HashSet};
+use bevy_xpbd_2d::prelude::*;
+use leafwing_input_manager::prelude::*;
+use rand::prelude::*;
+
+use crate::animation::{
+    self, AnimationCue, AnimationEvent, AnimationIndices, AnimationSheet, AnimationSheetLoader,
+};
+use crate::audio::PlaySound;
+use crate::campaign::{self, Campaign, LevelProgress};
+use crate::collisions::{self, BallHitPaddle, BallHitPlayer, BallHitRock, BallHitWall};
+use crate::controls::Controls;
+use crate::difficulty::Difficulty;
+use crate::level::{BallSettings, Level, LevelLoader};
+use crate::lives;
+use crate::{
+    gameplay_running, paddle, player, remove_all_sprites, replay, Action, GameMode, GameOptions,
+    GameState, LDAssets, Layer, PaddleAction, Randomizer, BOTTOM_WALL, LEFT_WALL,
+    PADDLE_LAUNCH_COOLDOWN, PADDLE_SIZE, PADDLE_SPEED, RIGHT_WALL, TOP_WALL,
+    WALL_THICKNESS,
+};
+
+/// The arena, the player, the paddle and its bombs.
+///
+/// Requires [`LDAssets`], [`GameOptions`], [`GameState`] and `PauseState`,
+/// plus the asset, physics and `Action`/`PaddleAction` input manager plugins. The
+/// [`Randomizer`], [`Campaign`], [`Difficulty`], [`GameMode`], [`Controls`]
+/// and [`replay::InputReplay`] are created if the host didn't provide them.
+pub(crate) struct GameplayPlugin;
+
+impl Plugin for GameplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PlaySound>()
+            .add_event::<AnimationEvent>()
+            .add_event::<BallHitRock>()
+            .add_event::<BallHitWall>()
+            .add_event::<BallHitPlayer>()
+            .add_event::<BallHitPaddle>()
+            .init_resource::<Randomizer>()
+            .init_resource::<replay::InputReplay>()
+            .insert_resource(player::PlayerAnimationTable::default())
+            .init_resource::<player::PlayerMovementConfig>()
+            .insert_resource(Gravity(Vec2::new(0.0, -800.0)))
+            .add_asset::<Level>()
+            .init_asset_loader::<LevelLoader>()
+            .add_asset::<AnimationSheet>()
+            .init_asset_loader::<AnimationSheetLoader>()
+            .init_resource::<BallSettings>()
+            .init_resource::<Campaign>()
+            .init_resource::<LevelProgress>()
+            .init_resource::<paddle::PaddlePrediction>()
+            .init_resource::<Difficulty>()
+            .init_resource::<GameMode>()
+            .init_resource::<LaunchCooldown>()
+            .init_resource::<lives::Lives>()
+            .init_resource::<Controls>()
+            .insert_resource(BallSpawnTimer::new(&BallSettings::default()))
+            .insert_resource(WalkSoundStatus { last_sound: 2 })
+            .add_systems(OnEnter(GameState::Playing), playing_setup)
+            .add_systems(OnEnter(GameState::Playing), replay::start_replay.after(playing_setup))
+            .add_systems(OnExit(GameState::Playing), replay::finish_replay)
+            .add_systems(OnExit(GameState::Playing), remove_all_sprites)
+            .add_systems(OnExit(GameState::LevelComplete), campaign::advance_campaign)
+            .add_systems(
+                Update,
+                (
+                    replay::replay_inputs.before(player_inputs),
+                    update_footing.before(player_inputs),
+                    player_inputs,
+                    animation::animate,
+                    lives::tick_invulnerability,
+                )
+                    .run_if(gameplay_running),
+            )
+            .add_systems(
+                Update,
+                (
+                    collisions::emit_ball_hits,
+                    (break_rocks, ball_wall_hits, ball_bump_sounds),
+                )
+                    .chain()
+                    .run_if(gameplay_running),
+            )
+            .add_systems(Update, normalize_ball_speed.run_if(gameplay_running))
+            .add_systems(
+                Update,
+                load_player_animations.run_if(resource_exists::<LDAssets>()),
+            )
+            .add_systems(
+                Update,
+                (
+                    player_animation.after(animation::animate),
+                    footstep_sounds.after(player_animation),
+                    paddle::predict_interceptions.before(paddle_ai),
+                    (paddle_ai, spawn_ball_timer).run_if(resource_equals(GameMode::Solo)),
+                    paddle_inputs.run_if(resource_equals(GameMode::Versus)),
+                    // If the player dies on the last second, dying wins.
+                    campaign::check_for_level_complete.before(check_for_gg),
+                    check_for_gg,
+                )
+                    .run_if(gameplay_running),
+            );
+    }
+}
+
+/// Heads for wherever the next ball is going to come up, bounces and all.
+/// How quickly and how accurately depends on the [`Difficulty`].
+fn paddle_ai(
+    time: Res<Time>,
+    difficulty: Res<Difficulty>,
+    prediction: Res<paddle::PaddlePrediction>,
+    mut rng: ResMut<Randomizer>,
+    mut paddle_query: Query<(&mut paddle::Paddle, &mut Transform)>,
+) {
+    let Ok((mut paddle, mut paddle_transform)) = paddle_query.get_single_mut() else {
+        return;
+    };
+    let Some(target) = prediction.soonest() else {
+        paddle.chasing = None;
+        return;
+    };
+    let profile = difficulty.profile();
+
+    if paddle.chasing != Some(target.ball) {
+        paddle.chasing = Some(target.ball);
+        paddle.reaction = profile.reaction_delay;
+        paddle.aim_error = if profile.prediction_error > 0.0 {
+            rng.paddle
+                .gen_range(-profile.prediction_error..=profile.prediction_error)
+        } else {
+            0.0
+        };
+    }
+    if paddle.reaction > 0.0 {
+        paddle.reaction -= time.delta_seconds();
+        return;
+    }
+
+    let half_width = PADDLE_SIZE.x / 2.0;
+    let target_x = (target.point.x + paddle.aim_error)
+        .clamp(LEFT_WALL + half_width, RIGHT_WALL - half_width);
+    let offset = target_x - paddle_transform.translation.x;
+    let amount = profile.paddle_speed * time.delta().as_secs_f32();
+
+    // Don't overshoot and jitter back and forth once we're there.
+    if offset.abs() <= amount {
+        paddle_transform.translation.x = target_x;
+    } else {
+        paddle.left = offset < 0.0;
+        paddle_transform.translation.x += amount.copysign(offset);
+    }
+}
+
+fn playing_setup(
+    assets: Res<LDAssets>,
+    levels: Res<Assets<Level>>,
+    campaign: Res<Campaign>,
+    config: Res<GameOptions>,
+    difficulty: Res<Difficulty>,
+    mode: Res<GameMode>,
+    controls: Res<Controls>,
+    gamepads: Res<Gamepads>,
+    mut rng: ResMut<Randomizer>,
+    mut commands: Commands,
+    player_animations: Res<player::PlayerAnimationTable>,
+) {
+    // Reseed every run so a fixed seed replays the same run, not just the
+    // first one after launch.
+    *rng = Randomizer::from_seed_option(config.seed);
+    let level = campaign
+        .current(&levels)
+        .expect("Level should be loaded before playing");
+    let paddle_start = Vec2::from(level.paddle_start).extend(4.0);
+
+    commands.spawn(SpriteBundle {
+        texture: assets.gamebg.clone(),
+        ..default()
+    });
+    commands
+        .spawn(WallBundle::new(WallLocation::Left, false));
+    commands
+        .spawn(WallBundle::new(WallLocation::Right, false));
+    commands
+        .spawn(WallBundle::new(WallLocation::Bottom, false));
+    commands
+        .spawn(WallBundle::new(WallLocation::Top, true));
+
+    let paddle = commands
+        .spawn(paddle::PaddleBundle::new(&assets, paddle_start))
+        .id();
+    let player = player::PlayerBundle::new(
+        &assets,
+        &player_animations,
+        controls.input_map(*mode),
+        Vec2::from(level.player_start).extend(1.0),
+    );
+    match *mode {
+        GameMode::Solo => {
+            commands.spawn(player);
+        }
+        GameMode::Versus => {
+            // Whatever ids the pads got, the lowest is the player's and the
+            // next one the paddle's.
+            let mut pads: Vec<_> = gamepads.iter().collect();
+            pads.sort_by_key(|gamepad| gamepad.id);
+            commands.entity(paddle).insert(InputManagerBundle::<PaddleAction> {
+                input_map: paddle::paddle_input_map(pads.get(1).copied()),
+                ..default()
+            });
+            match pads.first() {
+                Some(&gamepad) => commands.spawn(player.with_gamepad(gamepad)),
+                None => commands.spawn(player),
+            };
+            commands.insert_resource(LaunchCooldown::default());
+        }
+    }
+    commands.insert_resource(lives::Lives {
+        left: config.lives,
+    });
+
+    let mut ball_settings = level.balls.clone();
+    ball_settings.spawn_interval *= difficulty.profile().spawn_interval_scale;
+
+    let mut progress = LevelProgress::new(level.goal);
+    // In versus the paddle player throws the first one too.
+    if *mode == GameMode::Solo && progress.take_bomb() {
+        commands.spawn(BallBundle::new(&assets, &mut rng, &ball_settings, paddle_start));
+    }
+    commands.insert_resource(progress);
+    commands.insert_resource(BallSpawnTimer::new(&ball_settings));
+    commands.insert_resource(ball_settings);
+
+    spawn_rocks(level, rng, &mut commands, &assets);
+}
+
+fn spawn_rocks(level: &Level, mut rng: ResMut<'_, Randomizer>, commands: &mut Commands<'_, '_>, assets: &Res<'_, LDAssets>) {
+    let image_indices: [usize; 2] = [0, 1];
+
+    for rock in &level.rocks {
+        let image_index = rock
+            .kind
+            .image_index()
+            .unwrap_or_else(|| *image_indices.choose(&mut rng.rocks).unwrap());
+        commands
+            .spawn(RockBundle::new(assets, image_index, Vec2::from(rock.position)));
+    }
+}
+
+/// Walking slower than this fraction of full speed doesn't slow the walk
+/// cycle, and its footsteps, down any further.
+const MIN_WALK_CADENCE: f32 = 0.25;
+
+#[derive(Resource)]
+struct WalkSoundStatus {
+    last_sound: i8,
+}
+
+#[derive(Component, Default)]
+pub(crate) struct Rock;
+
+#[derive(Bundle)]
+struct RockBundle {
+    rock: Rock,
+    #[bundle()]
+    sprite: SpriteSheetBundle,
+    collider: Collider,
+    rigid_body: RigidBody,
+    collision_layer: CollisionLayers,
+    sleeping_disabled: SleepingDisabled,
+}
+
+impl RockBundle {
+    fn new(assets: &Res<LDAssets>, image_index: usize, rock_position: Vec2) -> RockBundle {
+        RockBundle {
+            sprite: SpriteSheetBundle {
+                texture_atlas: assets.rocks.clone(),
+                sprite: TextureAtlasSprite {
+                    index: image_index,
+                    ..default()
+                },
+                transform: Transform {
+                    translation: rock_position.extend(1.0),
+                    ..default()
+                },
+                ..default()
+            },
+            rock: Rock,
+            // collider: Collider::cuboid(60.0, if image_index == 1 { 18.0 } else { 25.0 }),
+            rigid_body: RigidBody::Static,
+            collision_layer: CollisionLayers::new([Layer::Rock], [Layer::Ball, Layer::Player]),
+            sleeping_disabled: SleepingDisabled,
+            collider: Collider::capsule_endpoints(
+                Vec2::new(-20.0, 0.0),
+                Vec2::new(20.0, 0.0),
+                if image_index == 1 { 13.0 } else { 15.0 },
+            ),
+
+        }
+    }
+}
+
+#[derive(Component, Default)]
+pub(crate) struct Ball;
+
+#[derive(Bundle)]
+struct BallBundle {
+    ball: Ball,
+    #[bundle()]
+    sprite: SpriteBundle,
+    collider: Collider,
+    rigid_body: RigidBody,
+    linear_velocity: LinearVelocity,
+    restitution: Restitution,
+    friction: Friction,
+    gravity_scale: GravityScale,
+    collision_layer: CollisionLayers,
+    sleeping_disabled: SleepingDisabled,
+}
+
+impl BallBundle {
+    fn new(
+        assets: &Res<LDAssets>,
+        rng: &mut Randomizer,
+        settings: &BallSettings,
+        paddle_location: Vec3,
+    ) -> BallBundle {
+        // Randomize starting direction of ball
+        let spread = settings.spread.to_radians();
+        let angle = if spread > 0.0 {
+            rng.balls.gen_range(-spread..spread)
+        } else {
+            0.0
+        };
+        let rotation = Quat::from_axis_angle(Vec3::Z, angle);
+        let start_velocity = rotation
+            .mul_vec3(Vec3::new(0., -settings.speed, 0.))
+            .truncate();
+
+        BallBundle {
+            ball: Ball,
+            sprite: SpriteBundle {
+                sprite: Sprite {
+                    anchor: Anchor::Custom(Vec2::new(0.0, -0.1)),
+                    ..Default::default()
+                },
+                texture: assets.bomb.clone(),
+                transform: Transform::from_translation(
+                    paddle_location + Vec3::new(0., (-PADDLE_SIZE.y / 2.) - 10., 0.),
+                ),
+                ..default()
+            },
+            rigid_body: RigidBody::Dynamic,
+            collider: Collider::ball(10.0),
+            linear_velocity: LinearVelocity(start_velocity),
+            // external_force: ExternalForce::new(start_velocity * 10000.0).with_persistence(false),
+            restitution: Restitution::new(1.0).with_combine_rule(CoefficientCombine::Max),
+            friction: Friction::ZERO,
+            gravity_scale: GravityScale(0.0),
+            collision_layer: CollisionLayers::new(
+                [Layer::Ball],
+                [
+                    Layer::Rock,
+                    Layer::Player,
+                    Layer::Paddle,
+                    Layer::Wall,
+                    Layer::Ball,
+                ],
+            ),
+            sleeping_disabled: SleepingDisabled,
+        }
+    }
+}
+
+#[derive(Resource)]
+struct BallSpawnTimer(Timer);
+
+impl BallSpawnTimer {
+    fn new(settings: &BallSettings) -> Self {
+        BallSpawnTimer(Timer::new(
+            Duration::from_secs_f32(settings.spawn_interval),
+            TimerMode::Repeating,
+        ))
+    }
+}
+
+fn spawn_ball_timer(
+    time: Res<Time>,
+    assets: Res<LDAssets>,
+    mut rng: ResMut<Randomizer>,
+    settings: Res<BallSettings>,
+    mut progress: ResMut<LevelProgress>,
+    mut ball_timer: ResMut<BallSpawnTimer>,
+    mut commands: Commands,
+    paddle: Query<&Transform, With<paddle::Paddle>>,
+) {
+    let Ok(paddle_xform) = paddle.get_single() else {
+        return;
+    };
+
+    ball_timer.0.tick(time.delta());
+
+    if ball_timer.0.just_finished() && progress.take_bomb() {
+        commands.spawn(BallBundle::new(
+            &assets,
+            &mut rng,
+            &settings,
+            paddle_xform.translation,
+        ));
+    }
+}
+
+/// Keeps the paddle player from emptying the whole bag at once.
+#[derive(Resource)]
+struct LaunchCooldown(Timer);
+
+impl Default for LaunchCooldown {
+    fn default() -> Self {
+        LaunchCooldown(Timer::from_seconds(PADDLE_LAUNCH_COOLDOWN, TimerMode::Once))
+    }
+}
+
+/// Drives the paddle in [`GameMode::Versus`]: move it around and launch bombs
+/// whenever the cooldown allows.
+fn paddle_inputs(
+    time: Res<Time>,
+    assets: Res<LDAssets>,
+    mut rng: ResMut<Randomizer>,
+    settings: Res<BallSettings>,
+    mut progress: ResMut<LevelProgress>,
+    mut cooldown: ResMut<LaunchCooldown>,
+    mut commands: Commands,
+    mut paddle_query: Query<(
+        &mut paddle::Paddle,
+        &mut Transform,
+        &ActionState<PaddleAction>,
+    )>,
+) {
+    let Ok((mut paddle, mut paddle_transform, action_state)) = paddle_query.get_single_mut() else {
+        return;
+    };
+
+    let movement = action_state.value(PaddleAction::Move);
+    if movement != 0.0 {
+        let half_width = PADDLE_SIZE.x / 2.0;
+        paddle.left = movement < 0.0;
+        paddle_transform.translation.x = (paddle_transform.translation.x
+            + movement * PADDLE_SPEED * time.delta_seconds())
+        .clamp(LEFT_WALL + half_width, RIGHT_WALL - half_width);
+    }
+
+    cooldown.0.tick(time.delta());
+    if action_state.just_pressed(PaddleAction::Launch)
+        && cooldown.0.finished()
+        && progress.take_bomb()
+    {
+        cooldown.0.reset();
+        commands.spawn(BallBundle::new(
+            &assets,
+            &mut rng,
+            &settings,
+            paddle_transform.translation,
+        ));
+    }
+}
+
+enum WallLocation {
+    Left,
+    Right,
+    Bottom,
+    Top,
+}
+
+impl WallLocation {
+    fn position(&self) -> Vec2 {
+        match self {
+            WallLocation::Left => Vec2::new(LEFT_WALL - WALL_THICKNESS / 2., 0.),
+            WallLocation::Right => Vec2::new(RIGHT_WALL + WALL_THICKNESS / 2., 0.),
+            WallLocation::Bottom => Vec2::new(0., BOTTOM_WALL - WALL_THICKNESS / 2.),
+            WallLocation::Top => Vec2::new(0., TOP_WALL + WALL_THICKNESS / 2.),
+        }
+    }
+
+    fn size(&self) -> Vec2 {
+        let arena_height = TOP_WALL - BOTTOM_WALL;
+        let arena_width = RIGHT_WALL - LEFT_WALL;
+        // Make sure we haven't messed up our constants
+        assert!(arena_height > 0.0);
+        assert!(arena_width > 0.0);
+
+        match self {
+            WallLocation::Left | WallLocation::Right => {
+                Vec2::new(WALL_THICKNESS, arena_height + WALL_THICKNESS)
+            }
+            WallLocation::Bottom | WallLocation::Top => {
+                Vec2::new(arena_width + WALL_THICKNESS, WALL_THICKNESS)
+            }
+        }
+    }
+}
+
+#[derive(Component)]
+pub(crate) struct Wall {
+    pub(crate) ball_destroyer: bool,
+}
+
+#[derive(Bundle)]
+struct WallBundle {
+    wall: Wall,
+    sprite_bundle: SpriteBundle,
+    collider: Collider,
+    rigid_body: RigidBody,
+    restitution: Restitution,
+    collision_layer: CollisionLayers,
+}
+
+impl WallBundle {
+    // This "builder method" allows us to reuse logic across our wall entities,
+    // making our code easier to read and less prone to bugs when we change the logic
+    fn new(location: WallLocation, ball_destroyer: bool) -> WallBundle {
+        WallBundle {
+            wall: Wall { ball_destroyer },
+            rigid_body: RigidBody::Static,
+            sprite_bundle: SpriteBundle {
+                transform: Transform {
+                    // We need to convert our Vec2 into a Vec3, by giving it a z-coordinate
+                    // This is used to determine the order of our sprites
+                    translation: location.position().extend(0.0),
+                    // The z-scale of 2D objects must always be 1.0,
+                    // or their ordering will be affected in surprising ways.
+                    // See https://github.com/bevyengine/bevy/issues/4149
+                    scale: location.size().extend(1.0),
+                    ..default()
+                },
+                sprite: Sprite { ..default() },
+                ..default()
+            },
+            collider: Collider::cuboid(location.size().x, location.size().y),
+            restitution: Restitution::new(1.0).with_combine_rule(CoefficientCombine::Max),
+            collision_layer: CollisionLayers::new(
+                [Layer::Wall],
+                [Layer::Ball, Layer::Player, Layer::Rock],
+            ),
+        }
+    }
+}
+
+/// Rising faster than this means they've just jumped, even if the ground is
+/// still in reach.
+const MAX_GROUNDED_RISE: f32 = 1.0;
+
+fn update_footing(
+    time: Res<Time>,
+    mut player_query: Query<
+        (&mut player::Footing, &ShapeHits, &LinearVelocity),
+        With<player::Player>,
+    >,
+) {
+    for (mut footing, hits, velocity) in &mut player_query {
+        footing.grounded = hits.iter().next().is_some() && velocity.y <= MAX_GROUNDED_RISE;
+        footing.since_jump_pressed += time.delta_seconds();
+        if footing.grounded {
+            footing.since_grounded = 0.0;
+        } else {
+            footing.since_grounded += time.delta_seconds();
+        }
+    }
+}
+
+fn player_inputs(
+    mut player_query: Query<
+        (
+            &mut LinearVelocity,
+            &mut player::Footing,
+            &Transform,
+            &ActionState<Action>,
+        ),
+        With<player::Player>,
+    >,
+    mut sounds: EventWriter<PlaySound>,
+    assets: Res<LDAssets>,
+    config: Res<player::PlayerMovementConfig>,
+    time: Res<Time>,
+) {
+    let Ok((mut velocity, mut footing, transform, action_state)) = player_query.get_single_mut()
+    else {
+        return;
+    };
+    let x = transform.translation.x;
+
+    // Keys give a full step either way, the stick anything in between.
+    let target = if action_state.pressed(Action::Move) {
+        action_state.clamped_value(Action::Move) * config.max_speed
+    } else {
+        0.0
+    };
+    velocity.x = config.run(velocity.x, target, footing.grounded, time.delta_seconds());
+
+    if action_state.just_pressed(Action::Jump) {
+        footing.since_jump_pressed = 0.0;
+    }
+    if footing.since_jump_pressed <= config.jump_buffer_time
+        && footing.since_grounded <= config.coyote_time
+    {
+        velocity.y = config.jump_speed;
+        // A buffered jump that was let go of before landing is only a hop.
+        if !action_state.pressed(Action::Jump) {
+            velocity.y *= config.jump_cut;
+        }
+        // One press, one jump, and no second one off the same ledge.
+        footing.since_jump_pressed = f32::INFINITY;
+        footing.since_grounded = f32::INFINITY;
+        sounds.send(PlaySound::at(assets.jump_sound.clone(), x));
+    }
+    if action_state.just_released(Action::Jump) && velocity.y > 0.0 {
+        velocity.y *= config.jump_cut;
+    }
+}
+
+/// Falling out of the arena costs a life. Out of lives, the game's over.
+fn check_for_gg(
+    mut player_query: Query<(Entity, &mut Transform, &mut LinearVelocity), With<player::Player>>,
+    rocks: Query<&Transform, (With<Rock>, Without<player::Player>)>,
+    mut lives: ResMut<lives::Lives>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut sounds: EventWriter<PlaySound>,
+    mut commands: Commands,
+    assets: Res<LDAssets>,
+) {
+    let Ok((player, mut player_xform, mut player_velocity)) = player_query.get_single_mut() else {
+        return;
+    };
+
+    if player_xform.translation.y < -270.0 {
+        sounds.send(PlaySound::new(assets.explosion_sound.clone()));
+        if !lives::respawn(
+            &mut lives,
+            &mut commands,
+            player,
+            &mut player_xform,
+            &mut player_velocity,
+            &rocks,
+        ) {
+            next_state.set(GameState::GameOver);
+        }
+    }
+}
+
+/// Builds the [`player::PlayerAnimationTable`] from the Aseprite export once
+/// it's loaded, and again whenever it's re-exported.
+fn load_player_animations(
+    assets: Res<LDAssets>,
+    sheets: Res<Assets<AnimationSheet>>,
+    mut events: EventReader<AssetEvent<AnimationSheet>>,
+    mut table: ResMut<player::PlayerAnimationTable>,
+    mut loaded: Local<bool>,
+) {
+    let changed = events.iter().any(|event| match event {
+        AssetEvent::Created { handle } | AssetEvent::Modified { handle } => {
+            *handle == assets.player_animations
+        }
+        AssetEvent::Removed { .. } => false,
+    });
+    if *loaded && !changed {
+        return;
+    }
+    let Some(sheet) = sheets.get(&assets.player_animations) else {
+        return;
+    };
+    *loaded = true;
+    *table = player::PlayerAnimationTable::from_sheet(sheet);
+}
+
+/// Runs the player's [`player::PlayerAnimationState`] machine and starts
+/// the clip for whatever state it lands in.
+pub(crate) fn player_animation(
+    mut player_query: Query<
+        (
+            Entity,
+            &LinearVelocity,
+            &mut TextureAtlasSprite,
+            &mut AnimationIndices,
+            &mut player::PlayerAnimationState,
+            &player::Footing,
+            Option<Ref<lives::Invulnerable>>,
+        ),
+        With<player::Player>,
+    >,
+    player_animations: Res<player::PlayerAnimationTable>,
+    movement: Res<player::PlayerMovementConfig>,
+    mut events: EventWriter<AnimationEvent>,
+) {
+    let Ok((entity, velocity, mut atlas, mut animation, mut state, footing, invulnerable)) =
+        player_query.get_single_mut()
+    else {
+        return;
+    };
+
+    // Respawning after losing a life is what makes the player invulnerable.
+    let hurt = invulnerable.is_some_and(|invulnerable| invulnerable.is_added());
+    let next = state.next(
+        velocity.0,
+        footing.grounded,
+        hurt,
+        animation.finished(&atlas),
+    );
+    if next != *state || hurt {
+        *state = next;
+        if let Some(cue) = animation.play(&mut atlas, player_animations.clip(next)) {
+            events.send(AnimationEvent { entity, cue });
+        }
+    }
+
+    if *state == player::PlayerAnimationState::Walk {
+        // The legs, and so the footsteps, keep up with the stick.
+        animation.speed = (velocity.x.abs() / movement.max_speed).clamp(MIN_WALK_CADENCE, 1.0);
+    }
+
+    if velocity.x.abs() > 0.2 {
+        atlas.flip_x = velocity.x < 0.0;
+    }
+}
+
+/// Alternates the two footstep sounds on the walk cycle's footstep frames.
+fn footstep_sounds(
+    mut events: EventReader<AnimationEvent>,
+    players: Query<&Transform, With<player::Player>>,
+    mut sounds: EventWriter<PlaySound>,
+    assets: Res<LDAssets>,
+    mut walk_sound_status: ResMut<WalkSoundStatus>,
+) {
+    for event in &mut events {
+        if event.cue != AnimationCue::Footstep {
+            continue;
+        }
+        let Ok(transform) = players.get(event.entity) else {
+            continue;
+        };
+        let x = transform.translation.x;
+        if walk_sound_status.last_sound == 1 {
+            sounds.send(PlaySound::at(assets.step2_sound.clone(), x));
+            walk_sound_status.last_sound = 2;
+        } else {
+            sounds.send(PlaySound::at(assets.step1_sound.clone(), x));
+            walk_sound_status.last_sound = 1;
+        }
+    }
+}
+
+/// Keeps every bomb inside the level's speed band and heading up or down
+/// steeply enough to get somewhere, the rocks or the paddle's line. Bounces
+/// off the player and each other would otherwise slowly change both.
+fn normalize_ball_speed(
+    settings: Res<BallSettings>,
+    mut balls: Query<&mut LinearVelocity, With<Ball>>,
+) {
+    let (slowest, fastest) = settings.speed_band;
+    let min_vertical = settings.min_vertical;
+    for mut velocity in &mut balls {
+        let speed = velocity.length();
+        // Stopped dead, send it back down.
+        let direction = velocity.try_normalize().unwrap_or(Vec2::NEG_Y);
+        let direction = if direction.y.abs() < min_vertical {
+            let down_or_up = if direction.y > 0.0 { 1.0 } else { -1.0 };
+            Vec2::new(
+                direction.x.signum() * (1.0 - min_vertical * min_vertical).sqrt(),
+                down_or_up * min_vertical,
+            )
+        } else {
+            direction
+        };
+        let corrected =
+            direction * speed.clamp(slowest * settings.speed, fastest * settings.speed);
+        // Leave change detection alone when there's nothing to fix.
+        if corrected.distance_squared(velocity.0) > f32::EPSILON {
+            velocity.0 = corrected;
+        }
+    }
+}
+
+/// Several balls can hit the same rock at once, it only breaks the once.
+fn break_rocks(
+    mut commands: Commands,
+    mut hits: EventReader<BallHitRock>,
+    mut sounds: EventWriter<PlaySound>,
+    assets: Res<LDAssets>,
+) {
+    let mut broken = HashSet::new();
+    for hit in &mut hits {
+        if broken.insert(hit.rock) {
+            commands.entity(hit.rock).despawn_recursive();
+            sounds.send(PlaySound::at(assets.break_sound.clone(), hit.position.x));
+        }
+    }
+}
+
+fn ball_wall_hits(
+    mut commands: Commands,
+    mut hits: EventReader<BallHitWall>,
+    mut sounds: EventWriter<PlaySound>,
+    assets: Res<LDAssets>,
+) {
+    for hit in &mut hits {
+        if hit.ball_destroyer {
+            commands.entity(hit.ball).despawn_recursive();
+        } else {
+            sounds.send(PlaySound::at(assets.wall_sound.clone(), hit.position.x));
+        }
+    }
+}
+
+fn ball_bump_sounds(
+    mut player_hits: EventReader<BallHitPlayer>,
+    mut paddle_hits: EventReader<BallHitPaddle>,
+    mut sounds: EventWriter<PlaySound>,
+    mut rng: ResMut<Randomizer>,
+    assets: Res<LDAssets>,
+) {
+    for hit in &mut player_hits {
+        let source = if rng.sfx.gen_range(0..2) == 1 {
+            assets.ball_sound.clone()
+        } else {
+            assets.ball2_sound.clone()
+        };
+        sounds.send(PlaySound::at(source, hit.position.x));
+    }
+    for hit in &mut paddle_hits {
+        sounds.send(PlaySound::at(assets.ball2_sound.clone(), hit.position.x));
+    }
+}
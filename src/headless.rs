@@ -0,0 +1,200 @@
+use std::time::Duration;
+
+use bevy::{
+    asset::FileAssetIo, input::InputPlugin, prelude::*, time::TimeUpdateStrategy,
+};
+use bevy_xpbd_2d::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use crate::campaign::Campaign;
+use crate::gameplay::{Ball, GameplayPlugin, Rock};
+use crate::level::{Level, CAMPAIGN};
+use crate::{Action, GameOptions, GameState, LDAssets, PaddleAction, PauseState, Randomizer};
+
+/// What the arena looked like when a simulation stopped.
+#[derive(Debug, PartialEq)]
+pub(crate) struct SimulationReport {
+    pub(crate) frames: u32,
+    pub(crate) elapsed: Duration,
+    pub(crate) game_over: bool,
+    pub(crate) cleared: bool,
+    pub(crate) balls: usize,
+    pub(crate) rocks: usize,
+}
+
+impl std::fmt::Display for SimulationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} after {} frames ({:.2} s), {} balls and {} rocks left",
+            if self.game_over {
+                "Died"
+            } else if self.cleared {
+                "Cleared the level"
+            } else {
+                "Survived"
+            },
+            self.frames,
+            self.elapsed.as_secs_f32(),
+            self.balls,
+            self.rocks
+        )
+    }
+}
+
+/// There's no loading screen to wait on, so read the levels straight off the
+/// disk.
+fn load_level(level: &str) -> Level {
+    let path = FileAssetIo::get_base_path().join("assets").join(level);
+    let bytes = std::fs::read(&path)
+        .unwrap_or_else(|e| panic!("Couldn't read {}: {}", path.display(), e));
+    Level::from_bytes(&bytes).unwrap_or_else(|e| panic!("Bad level {}: {}", path.display(), e))
+}
+
+/// Nothing gets drawn or played, so every handle can point at nothing.
+fn placeholder_assets() -> LDAssets {
+    LDAssets {
+        font: Handle::default(),
+        player: Handle::default(),
+        player_animations: Handle::default(),
+        rocks: Handle::default(),
+        paddle: Handle::default(),
+        gameover: Handle::default(),
+        gamebg: Handle::default(),
+        splash: Handle::default(),
+        bomb: Handle::default(),
+        ball_sound: Handle::default(),
+        ball2_sound: Handle::default(),
+        break_sound: Handle::default(),
+        explosion_sound: Handle::default(),
+        jump_sound: Handle::default(),
+        step1_sound: Handle::default(),
+        step2_sound: Handle::default(),
+        wall_sound: Handle::default(),
+        bgm: Handle::default(),
+    }
+}
+
+/// Builds an app with just the gameplay systems: no window, renderer or
+/// audio. Every frame advances the clock by exactly `delta`, and the app
+/// starts straight in [`GameState::Playing`] on the `level` option's level.
+pub(crate) fn headless_app(game_options: GameOptions, delta: Duration) -> App {
+    let mut app = App::new();
+    let start_level = game_options.level;
+
+    app.add_plugins((
+        MinimalPlugins,
+        AssetPlugin::default(),
+        TransformPlugin,
+        HierarchyPlugin,
+        InputPlugin,
+        InputManagerPlugin::<Action>::default(),
+        InputManagerPlugin::<PaddleAction>::default(),
+        // The debug renderer wants gizmos, which need a renderer.
+        PhysicsPlugins::default()
+            .build()
+            .disable::<PhysicsDebugPlugin>(),
+    ))
+    .add_state::<GameState>()
+    .add_state::<PauseState>()
+    .insert_resource(TimeUpdateStrategy::ManualDuration(delta))
+    .insert_resource(Randomizer::from_seed_option(game_options.seed))
+    .insert_resource(game_options.difficulty)
+    .insert_resource(game_options.mode)
+    .insert_resource(game_options)
+    .add_plugins(GameplayPlugin);
+
+    let mut level_assets = app.world.resource_mut::<Assets<Level>>();
+    let levels = CAMPAIGN
+        .iter()
+        .map(|path| level_assets.add(load_level(path)))
+        .collect();
+    app.insert_resource(Campaign {
+        level: start_level,
+        levels,
+    })
+    .insert_resource(placeholder_assets());
+
+    app.world
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::Playing);
+
+    app.finish();
+    app.cleanup();
+    app
+}
+
+/// Steps the app until the player dies, the level is cleared or `frames`
+/// frames have gone by.
+pub(crate) fn simulate(app: &mut App, frames: u32) -> SimulationReport {
+    let mut ran = 0;
+    let mut game_over = false;
+    let mut cleared = false;
+    let mut balls = 0;
+    let mut rocks = 0;
+    let mut ball_query = app.world.query_filtered::<(), With<Ball>>();
+    let mut rock_query = app.world.query_filtered::<(), With<Rock>>();
+    let start = app.world.resource::<Time>().elapsed();
+
+    while ran < frames {
+        app.update();
+        ran += 1;
+        match app.world.resource::<State<GameState>>().get() {
+            GameState::GameOver => game_over = true,
+            GameState::LevelComplete => cleared = true,
+            // Leaving Playing clears the arena, so count while it's still up.
+            _ => {
+                balls = ball_query.iter(&app.world).count();
+                rocks = rock_query.iter(&app.world).count();
+                continue;
+            }
+        }
+        break;
+    }
+
+    let elapsed = app.world.resource::<Time>().elapsed() - start;
+
+    SimulationReport {
+        frames: ran,
+        elapsed,
+        game_over,
+        cleared,
+        balls,
+        rocks,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::difficulty::Difficulty;
+    use crate::GameMode;
+
+    fn options(seed: u64) -> GameOptions {
+        GameOptions {
+            debug: false,
+            skip: true,
+            seed: Some(seed),
+            record: None,
+            replay: None,
+            level: 0,
+            headless: None,
+            difficulty: Difficulty::default(),
+            mode: GameMode::Solo,
+            lives: 3,
+        }
+    }
+
+    fn run(seed: u64, frames: u32) -> SimulationReport {
+        let mut app = headless_app(options(seed), Duration::from_secs_f32(1.0 / 60.0));
+        simulate(&mut app, frames)
+    }
+
+    #[test]
+    fn same_seed_same_run() {
+        let first = run(1234, 1200);
+        let second = run(1234, 1200);
+        assert_eq!(first, second);
+        assert!(first.frames > 0);
+    }
+}
@@ -0,0 +1,271 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::{TypePath, TypeUuid},
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+
+use crate::{
+    BALL_SPAWN_INTERVAL, BALL_SPEED, BOTTOM_WALL, LEFT_WALL, RIGHT_WALL, ROCK_HEIGHT, ROCK_WIDTH,
+    TOP_WALL,
+};
+
+/// The campaign in order, relative to the assets folder.
+pub(crate) const CAMPAIGN: [&str; 3] = [
+    "levels/arena.level.ron",
+    "levels/pillars.level.ron",
+    "levels/rubble.level.ron",
+];
+
+/// An arena layout, loaded from a `.level.ron` file in `assets/levels`.
+///
+/// Positions are the centers of things in world space, the same as a
+/// `Transform` translation.
+#[derive(Deserialize, TypeUuid, TypePath, Debug)]
+#[uuid = "4d5c2a8e-5c0b-4f7e-9a3b-3b1f0c6d2e71"]
+pub(crate) struct Level {
+    /// Shown when the level is cleared.
+    #[serde(default)]
+    pub(crate) name: String,
+    #[serde(default)]
+    pub(crate) goal: Goal,
+    pub(crate) player_start: (f32, f32),
+    pub(crate) paddle_start: (f32, f32),
+    #[serde(default)]
+    pub(crate) balls: BallSettings,
+    pub(crate) rocks: Vec<RockPlacement>,
+}
+
+/// What the player has to do to clear a level.
+#[derive(Deserialize, Default, Clone, Copy, Debug)]
+pub(crate) enum Goal {
+    /// Stay alive for this many seconds.
+    Survive(f32),
+    /// The paddle only has this many bombs. Clear the level by surviving
+    /// until the last one is gone.
+    OutlastBombs(u32),
+    /// Never clears, play until you drop.
+    #[default]
+    Endless,
+}
+
+/// How the paddle throws bombs.
+#[derive(Deserialize, Resource, Clone, Debug)]
+pub(crate) struct BallSettings {
+    pub(crate) speed: f32,
+    /// Seconds between bombs.
+    pub(crate) spawn_interval: f32,
+    /// Bombs are thrown straight down, give or take this many degrees.
+    pub(crate) spread: f32,
+    /// Bombs are kept between these fractions of `speed`, so bounces can't
+    /// wind them up or run them down over time.
+    #[serde(default = "default_speed_band")]
+    pub(crate) speed_band: (f32, f32),
+    /// At least this fraction of a bomb's speed goes up or down, so none of
+    /// them skim sideways forever.
+    #[serde(default = "default_min_vertical")]
+    pub(crate) min_vertical: f32,
+}
+
+impl Default for BallSettings {
+    fn default() -> Self {
+        BallSettings {
+            speed: BALL_SPEED,
+            spawn_interval: BALL_SPAWN_INTERVAL,
+            spread: 45.0,
+            speed_band: (0.9, 1.1),
+            min_vertical: 0.3,
+        }
+    }
+}
+
+fn default_speed_band() -> (f32, f32) {
+    BallSettings::default().speed_band
+}
+
+fn default_min_vertical() -> f32 {
+    BallSettings::default().min_vertical
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct RockPlacement {
+    pub(crate) position: (f32, f32),
+    #[serde(default)]
+    pub(crate) kind: RockKind,
+}
+
+#[derive(Deserialize, Default, Clone, Copy, Debug)]
+pub(crate) enum RockKind {
+    Large,
+    Small,
+    /// Let the seed pick.
+    #[default]
+    Random,
+}
+
+impl RockKind {
+    /// Index into the rocks texture atlas, or `None` if it's up to the dice.
+    pub(crate) fn image_index(self) -> Option<usize> {
+        match self {
+            RockKind::Large => Some(0),
+            RockKind::Small => Some(1),
+            RockKind::Random => None,
+        }
+    }
+}
+
+fn in_arena((x, y): (f32, f32)) -> bool {
+    (LEFT_WALL..=RIGHT_WALL).contains(&x) && (BOTTOM_WALL..=TOP_WALL).contains(&y)
+}
+
+impl Level {
+    /// Checks that everything fits in the arena and that no two rocks sit on
+    /// top of each other.
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        if !in_arena(self.player_start) {
+            return Err(format!(
+                "Player starts at {:?}, outside the arena",
+                self.player_start
+            ));
+        }
+        if !in_arena(self.paddle_start) {
+            return Err(format!(
+                "Paddle starts at {:?}, outside the arena",
+                self.paddle_start
+            ));
+        }
+        if self.balls.speed <= 0.0 || self.balls.spawn_interval <= 0.0 {
+            return Err("Ball speed and spawn interval have to be positive".to_owned());
+        }
+        let (slowest, fastest) = self.balls.speed_band;
+        if !(0.0 < slowest && slowest <= 1.0 && 1.0 <= fastest) {
+            return Err(format!(
+                "Ball speed band {:?} has to go from above 0 to at least 1",
+                self.balls.speed_band
+            ));
+        }
+        if !(0.0..1.0).contains(&self.balls.min_vertical) {
+            return Err("Ball min_vertical has to be at least 0 and less than 1".to_owned());
+        }
+        match self.goal {
+            Goal::Survive(seconds) if seconds <= 0.0 => {
+                return Err("Survive goal has to be positive".to_owned());
+            }
+            Goal::OutlastBombs(0) => {
+                return Err("OutlastBombs goal needs at least one bomb".to_owned());
+            }
+            _ => {}
+        }
+
+        let half_rock = (ROCK_WIDTH / 2.0, ROCK_HEIGHT / 2.0);
+        for (i, rock) in self.rocks.iter().enumerate() {
+            let (x, y) = rock.position;
+            if !in_arena((x - half_rock.0, y - half_rock.1))
+                || !in_arena((x + half_rock.0, y + half_rock.1))
+            {
+                return Err(format!(
+                    "Rock {} at {:?} doesn't fit inside the arena",
+                    i, rock.position
+                ));
+            }
+            for (j, other) in self.rocks.iter().enumerate().take(i) {
+                let (ox, oy) = other.position;
+                if (x - ox).abs() < ROCK_WIDTH && (y - oy).abs() < ROCK_HEIGHT {
+                    return Err(format!(
+                        "Rock {} at {:?} overlaps rock {} at {:?}",
+                        i, rock.position, j, other.position
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<Level, String> {
+        let level: Level = ron::de::from_bytes(bytes).map_err(|e| e.to_string())?;
+        level.validate()?;
+        Ok(level)
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct LevelLoader;
+
+impl AssetLoader for LevelLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let level = Level::from_bytes(bytes).map_err(|e| {
+                bevy::asset::Error::msg(format!(
+                    "Bad level {}: {}",
+                    load_context.path().display(),
+                    e
+                ))
+            })?;
+            load_context.set_default_asset(LoadedAsset::new(level));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level.ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(rocks: &[(f32, f32)]) -> Level {
+        Level {
+            name: String::new(),
+            goal: Goal::Endless,
+            player_start: (0.0, -200.0),
+            paddle_start: (0.0, 250.0),
+            balls: BallSettings::default(),
+            rocks: rocks
+                .iter()
+                .map(|&position| RockPlacement {
+                    position,
+                    kind: RockKind::Random,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn accepts_good_level() {
+        assert_eq!(level(&[(-100.0, -250.0), (100.0, -250.0)]).validate(), Ok(()));
+    }
+
+    #[test]
+    fn rejects_overlapping_rocks() {
+        assert_eq!(
+            level(&[(0.0, -250.0), (30.0, -240.0)]).validate(),
+            Err("Rock 1 at (30.0, -240.0) overlaps rock 0 at (0.0, -250.0)".to_owned())
+        );
+    }
+
+    #[test]
+    fn rejects_rock_past_wall() {
+        assert_eq!(
+            level(&[(RIGHT_WALL - 10.0, 0.0)]).validate(),
+            Err("Rock 0 at (390.0, 0.0) doesn't fit inside the arena".to_owned())
+        );
+    }
+
+    #[test]
+    fn rejects_bad_speed_band() {
+        let mut level = level(&[]);
+        level.balls.speed_band = (1.2, 1.5);
+        assert_eq!(
+            level.validate(),
+            Err("Ball speed band (1.2, 1.5) has to go from above 0 to at least 1".to_owned())
+        );
+    }
+}
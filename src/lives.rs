@@ -0,0 +1,90 @@
+use bevy::prelude::*;
+use bevy_xpbd_2d::prelude::*;
+
+use crate::gameplay::Rock;
+use crate::player::{self, Player};
+use crate::ROCK_HEIGHT;
+
+/// How long the player blinks after respawning. Balls pass right through
+/// them until it's over.
+const INVULNERABLE_TIME: f32 = 2.0;
+/// Blinks per second while invulnerable.
+const BLINK_RATE: f32 = 8.0;
+
+/// Tries the player has left on the current level, counting the one in
+/// progress.
+#[derive(Resource)]
+pub(crate) struct Lives {
+    pub(crate) left: u32,
+}
+
+impl Default for Lives {
+    fn default() -> Self {
+        Lives { left: 3 }
+    }
+}
+
+#[derive(Component)]
+pub(crate) struct Invulnerable(Timer);
+
+/// Where the player comes back: just above the highest rock still standing,
+/// preferring the one nearest the middle. `None` if there's nothing left to
+/// stand on.
+fn respawn_point<'a>(rocks: impl Iterator<Item = &'a Transform>) -> Option<Vec3> {
+    rocks
+        .map(|transform| transform.translation)
+        .max_by(|a, b| {
+            a.y.total_cmp(&b.y)
+                .then_with(|| b.x.abs().total_cmp(&a.x.abs()))
+        })
+        .map(|top| Vec3::new(top.x, top.y + ROCK_HEIGHT / 2.0 + 40.0, 1.0))
+}
+
+/// Spends a life and puts the player back on a rock. Returns false when
+/// that was the last life, or there's nowhere left to put them.
+pub(crate) fn respawn(
+    lives: &mut Lives,
+    commands: &mut Commands,
+    player: Entity,
+    player_transform: &mut Transform,
+    player_velocity: &mut LinearVelocity,
+    rocks: &Query<&Transform, (With<Rock>, Without<Player>)>,
+) -> bool {
+    lives.left = lives.left.saturating_sub(1);
+    if lives.left == 0 {
+        return false;
+    }
+    let Some(position) = respawn_point(rocks.iter()) else {
+        return false;
+    };
+
+    player_transform.translation = position;
+    player_velocity.0 = Vec2::ZERO;
+    commands.entity(player).insert((
+        Invulnerable(Timer::from_seconds(INVULNERABLE_TIME, TimerMode::Once)),
+        player::player_collision_layers(false),
+    ));
+    true
+}
+
+/// Blinks invulnerable players and makes them solid again once their time is
+/// up.
+pub(crate) fn tick_invulnerability(
+    time: Res<Time>,
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Invulnerable, &mut TextureAtlasSprite)>,
+) {
+    for (entity, mut invulnerable, mut sprite) in &mut query {
+        invulnerable.0.tick(time.delta());
+        if invulnerable.0.finished() {
+            sprite.color.set_a(1.0);
+            commands
+                .entity(entity)
+                .remove::<Invulnerable>()
+                .insert(player::player_collision_layers(true));
+        } else {
+            let blink = (invulnerable.0.elapsed_secs() * BLINK_RATE) as u32 % 2 == 0;
+            sprite.color.set_a(if blink { 0.3 } else { 1.0 });
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use bevy::audio::{PlaybackMode, Volume, VolumeLevel};
+use bevy::prelude::*;
+
+use crate::gameplay::{Ball, Rock};
+use crate::settings::Settings;
+use crate::{gameplay_running, GameState, LDAssets, PauseState};
+
+/// Seconds for one track to fade out and the next to fade in.
+const CROSSFADE_TIME: f32 = 1.5;
+/// This many bombs in the air is as intense as it gets.
+const MAX_INTENSITY_BALLS: f32 = 6.0;
+/// How much quieter the gameplay track is when nothing is happening.
+const CALM_VOLUME: f32 = 0.6;
+/// How much faster the gameplay track plays at full intensity.
+const INTENSE_SPEEDUP: f32 = 0.12;
+/// How quickly the intensity follows the action, per second. Keeps a burst of
+/// bombs from yanking the tempo around.
+const INTENSITY_RATE: f32 = 0.5;
+
+/// Background music. Picks a track for the current [`GameState`], crossfades
+/// when it changes, and pushes the gameplay track harder as bombs pile up and
+/// rocks run out.
+///
+/// Requires [`LDAssets`], [`Settings`], [`GameState`] and [`PauseState`].
+pub(crate) struct MusicPlugin;
+
+impl Plugin for MusicPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MusicController>()
+            .add_systems(
+                Update,
+                choose_track.run_if(state_changed::<GameState>()),
+            )
+            .add_systems(OnEnter(GameState::Playing), reset_intensity)
+            .add_systems(Update, update_intensity.run_if(gameplay_running))
+            .add_systems(
+                Update,
+                (start_track, crossfade)
+                    .chain()
+                    .after(choose_track)
+                    .after(update_intensity)
+                    .run_if(resource_exists::<LDAssets>()),
+            )
+            .add_systems(OnEnter(PauseState::Paused), pause_music)
+            .add_systems(OnExit(PauseState::Paused), resume_music);
+    }
+}
+
+/// The jam only produced the one loop, so the tracks are that loop played at
+/// different speeds and volumes. Swap in real tracks in [`MusicTrack::source`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum MusicTrack {
+    Splash,
+    Gameplay,
+    GameOver,
+}
+
+impl MusicTrack {
+    fn source(self, assets: &LDAssets) -> Handle<AudioSource> {
+        assets.bgm.clone()
+    }
+
+    /// Volume and speed to play at, before the music volume setting.
+    fn mix(self, intensity: f32) -> (f32, f32) {
+        match self {
+            MusicTrack::Splash => (0.7, 0.85),
+            MusicTrack::Gameplay => (
+                CALM_VOLUME + (1.0 - CALM_VOLUME) * intensity,
+                1.0 + INTENSE_SPEEDUP * intensity,
+            ),
+            MusicTrack::GameOver => (0.5, 0.7),
+        }
+    }
+}
+
+/// What should be playing and how hard.
+#[derive(Resource, Default)]
+struct MusicController {
+    /// `None` fades everything out.
+    track: Option<MusicTrack>,
+    /// 0 when the level has just started, 1 when it's chaos.
+    intensity: f32,
+    /// The most rocks seen this level, to tell how many have been broken.
+    rocks_at_start: usize,
+}
+
+/// A playing track. Only the controller spawns and despawns these, nothing
+/// else should touch them.
+#[derive(Component)]
+struct Music {
+    track: MusicTrack,
+    /// 0 to 1, how far faded in.
+    fade: f32,
+}
+
+fn choose_track(state: Res<State<GameState>>, mut controller: ResMut<MusicController>) {
+    controller.track = match state.get() {
+        GameState::Loading | GameState::Setup => None,
+        GameState::Splash => Some(MusicTrack::Splash),
+        GameState::Playing | GameState::LevelComplete | GameState::Restarting => {
+            Some(MusicTrack::Gameplay)
+        }
+        GameState::GameOver => Some(MusicTrack::GameOver),
+    };
+}
+
+fn reset_intensity(mut controller: ResMut<MusicController>) {
+    controller.intensity = 0.0;
+    controller.rocks_at_start = 0;
+}
+
+/// Half from the bombs in the air, half from the rocks already broken.
+fn update_intensity(
+    time: Res<Time>,
+    balls: Query<(), With<Ball>>,
+    rocks: Query<(), With<Rock>>,
+    mut controller: ResMut<MusicController>,
+) {
+    let rocks_left = rocks.iter().count();
+    // The rocks are spawned with commands on the way into Playing, so count
+    // them once they're there.
+    controller.rocks_at_start = controller.rocks_at_start.max(rocks_left);
+    let broken = if controller.rocks_at_start == 0 {
+        0.0
+    } else {
+        1.0 - rocks_left as f32 / controller.rocks_at_start as f32
+    };
+    let busy = (balls.iter().count() as f32 / MAX_INTENSITY_BALLS).min(1.0);
+    let target = (busy + broken) / 2.0;
+
+    let step = INTENSITY_RATE * time.delta_seconds();
+    let intensity = controller.intensity;
+    let next = intensity + (target - intensity).clamp(-step, step);
+    // Don't trip change detection for nothing.
+    if (next - intensity).abs() > f32::EPSILON {
+        controller.intensity = next;
+    }
+}
+
+/// Starts the wanted track, silent, if it isn't already playing.
+fn start_track(
+    assets: Res<LDAssets>,
+    paused: Res<State<PauseState>>,
+    controller: Res<MusicController>,
+    music: Query<&Music>,
+    mut commands: Commands,
+) {
+    let Some(track) = controller.track else {
+        return;
+    };
+    if music.iter().any(|m| m.track == track) {
+        return;
+    }
+    let (_, speed) = track.mix(controller.intensity);
+    commands.spawn((
+        Music { track, fade: 0.0 },
+        AudioBundle {
+            source: track.source(&assets),
+            settings: PlaybackSettings {
+                mode: PlaybackMode::Loop,
+                volume: Volume::Absolute(VolumeLevel::new(0.0)),
+                speed,
+                paused: *paused.get() == PauseState::Paused,
+            },
+        },
+    ));
+}
+
+/// Fades the wanted track in and everything else out, and keeps the volumes
+/// in step with the settings and the intensity. The volume is set absolutely,
+/// so the master volume has to be put in here by hand.
+fn crossfade(
+    time: Res<Time>,
+    global_volume: Res<GlobalVolume>,
+    settings: Res<Settings>,
+    controller: Res<MusicController>,
+    mut tracks: Query<(Entity, &mut Music, Option<&AudioSink>)>,
+    mut commands: Commands,
+) {
+    let step = time.delta_seconds() / CROSSFADE_TIME;
+    for (entity, mut music, sink) in &mut tracks {
+        let wanted = controller.track == Some(music.track);
+        music.fade = if wanted {
+            (music.fade + step).min(1.0)
+        } else {
+            (music.fade - step).max(0.0)
+        };
+        if !wanted && music.fade <= 0.0 {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        // Not started yet, the asset is still loading.
+        let Some(sink) = sink else {
+            continue;
+        };
+        let (volume, speed) = music.track.mix(controller.intensity);
+        sink.set_volume(
+            volume * music.fade * settings.music_volume * global_volume.volume.get(),
+        );
+        sink.set_speed(speed);
+    }
+}
+
+fn pause_music(music: Query<&AudioSink, With<Music>>) {
+    for sink in &music {
+        sink.pause();
+    }
+}
+
+fn resume_music(music: Query<&AudioSink, With<Music>>) {
+    for sink in &music {
+        sink.play();
+    }
+}
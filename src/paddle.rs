@@ -1,11 +1,26 @@
 use bevy::prelude::*;
 
-use crate::{LDAssets, PADDLE_START};
+use crate::{gameplay::Ball, LDAssets, Layer, PaddleAction, PADDLE_SIZE};
 use bevy_xpbd_2d::prelude::*;
+use leafwing_input_manager::{axislike::VirtualAxis, prelude::*};
+
+/// Give up on a ball after this many bounces, it's not coming up any time
+/// soon.
+const MAX_PREDICTED_BOUNCES: usize = 8;
+/// How far along a ball's path we're willing to look.
+const MAX_PREDICTED_DISTANCE: f32 = 4000.0;
+/// How far off a surface the next leg of a predicted path starts.
+const SURFACE_OFFSET: f32 = 0.1;
 
 #[derive(Component)]
 pub(crate) struct Paddle {
     pub(crate) left: bool,
+    /// The ball the paddle is going for.
+    pub(crate) chasing: Option<Entity>,
+    /// Seconds left before it starts moving towards that ball.
+    pub(crate) reaction: f32,
+    /// How far off it's aiming for that ball.
+    pub(crate) aim_error: f32,
 }
 
 #[derive(Bundle)]
@@ -19,12 +34,17 @@ pub(crate) struct PaddleBundle {
 }
 
 impl PaddleBundle {
-    pub(crate) fn new(assets: &LDAssets) -> Self {
+    pub(crate) fn new(assets: &LDAssets, position: Vec3) -> Self {
         PaddleBundle {
-            paddle: Paddle { left: true },
+            paddle: Paddle {
+                left: true,
+                chasing: None,
+                reaction: 0.0,
+                aim_error: 0.0,
+            },
             sprite: SpriteBundle {
                 texture: assets.paddle.clone(),
-                transform: Transform::from_translation(PADDLE_START),
+                transform: Transform::from_translation(position),
                 ..Default::default()
             },
             collider: Collider::capsule_endpoints(
@@ -37,3 +57,143 @@ impl PaddleBundle {
         }
     }
 }
+
+/// The other half of the keyboard from the player, and `gamepad` if there's
+/// one to spare.
+pub(crate) fn paddle_input_map(gamepad: Option<Gamepad>) -> InputMap<PaddleAction> {
+    let mut input_map = InputMap::default();
+    input_map.insert(
+        UserInput::VirtualAxis(VirtualAxis {
+            negative: KeyCode::A.into(),
+            positive: KeyCode::D.into(),
+        }),
+        PaddleAction::Move,
+    );
+    input_map.insert(KeyCode::S, PaddleAction::Launch);
+    // Without one of its own the paddle would listen to the player's pad.
+    if let Some(gamepad) = gamepad {
+        input_map.insert(
+            UserInput::VirtualAxis(VirtualAxis {
+                negative: GamepadButtonType::DPadLeft.into(),
+                positive: GamepadButtonType::DPadRight.into(),
+            }),
+            PaddleAction::Move,
+        );
+        input_map.insert(GamepadButtonType::South, PaddleAction::Launch);
+        input_map.set_gamepad(gamepad);
+    }
+    input_map
+}
+
+/// Where and when a ball is going to reach the paddle.
+pub(crate) struct Interception {
+    pub(crate) ball: Entity,
+    /// Seconds until the ball gets there.
+    pub(crate) time: f32,
+    pub(crate) point: Vec2,
+    /// The ball's position followed by every bounce on the way.
+    pub(crate) path: Vec<Vec2>,
+}
+
+/// Every ball that's going to make it up to the paddle, soonest first.
+#[derive(Resource, Default)]
+pub(crate) struct PaddlePrediction {
+    pub(crate) interceptions: Vec<Interception>,
+}
+
+impl PaddlePrediction {
+    pub(crate) fn soonest(&self) -> Option<&Interception> {
+        self.interceptions.first()
+    }
+}
+
+/// Follows a ball along its path, bouncing off rocks and walls, until it
+/// crosses `line_y` going up. Treats the ball as a point, which is close
+/// enough for a paddle that's wider than it.
+fn trace_to_line(
+    spatial_query: &SpatialQuery,
+    filter: &SpatialQueryFilter,
+    start: Vec2,
+    velocity: Vec2,
+    line_y: f32,
+) -> Option<(f32, Vec2, Vec<Vec2>)> {
+    let speed = velocity.length();
+    if speed <= f32::EPSILON {
+        return None;
+    }
+
+    let mut position = start;
+    let mut direction = velocity / speed;
+    let mut travelled = 0.0;
+    let mut path = vec![start];
+
+    for _ in 0..=MAX_PREDICTED_BOUNCES {
+        let remaining = MAX_PREDICTED_DISTANCE - travelled;
+        let hit = spatial_query.cast_ray(position, direction, remaining, false, filter.clone());
+        let segment = hit.as_ref().map_or(remaining, |hit| hit.time_of_impact);
+
+        // Does this leg reach the paddle before it hits anything?
+        if direction.y > 0.0 && position.y < line_y {
+            let to_line = (line_y - position.y) / direction.y;
+            if to_line <= segment {
+                let point = position + direction * to_line;
+                path.push(point);
+                return Some(((travelled + to_line) / speed, point, path));
+            }
+        }
+
+        let hit = hit?;
+        position += direction * hit.time_of_impact;
+        travelled += hit.time_of_impact;
+        direction -= 2.0 * direction.dot(hit.normal) * hit.normal;
+        path.push(position);
+        // Start the next leg just off the surface, or rounding can put it a
+        // hair inside and the next cast hits the same collider straight away.
+        position += hit.normal * SURFACE_OFFSET;
+    }
+
+    None
+}
+
+pub(crate) fn predict_interceptions(
+    spatial_query: SpatialQuery,
+    mut prediction: ResMut<PaddlePrediction>,
+    paddle_query: Query<(Entity, &Transform), With<Paddle>>,
+    ball_query: Query<(Entity, &Transform, &LinearVelocity), With<Ball>>,
+) {
+    prediction.interceptions.clear();
+    let Ok((paddle, paddle_transform)) = paddle_query.get_single() else {
+        return;
+    };
+    let line_y = paddle_transform.translation.y - PADDLE_SIZE.y / 2.0;
+    // The paddle doesn't have layers of its own, so it has to be left out by
+    // hand.
+    let filter = SpatialQueryFilter::new()
+        .with_masks([Layer::Rock, Layer::Wall])
+        .without_entities([paddle]);
+
+    prediction.interceptions.extend(
+        ball_query
+            .iter()
+            // Ignore balls that are above the paddle
+            .filter(|(_, t, _)| t.translation.y < line_y)
+            .filter_map(|(ball, t, v)| {
+                trace_to_line(
+                    &spatial_query,
+                    &filter,
+                    t.translation.truncate(),
+                    v.0,
+                    line_y,
+                )
+                .map(|(time, point, path)| Interception {
+                    ball,
+                    time,
+                    point,
+                    path,
+                })
+            }),
+    );
+    prediction
+        .interceptions
+        .sort_by(|a, b| a.time.total_cmp(&b.time));
+}
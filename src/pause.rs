@@ -0,0 +1,182 @@
+use bevy::{prelude::*, window::WindowFocused};
+use bevy_xpbd_2d::prelude::*;
+
+use crate::settings::SettingsState;
+use crate::ui::{self, MenuInput};
+use crate::{gameplay_running, GameState, LDAssets, PauseState};
+
+/// Escape, Start or losing focus pauses the game and brings up the pause
+/// menu.
+///
+/// Requires [`LDAssets`], [`GameState`] and [`PauseState`], plus the physics
+/// plugins.
+pub(crate) struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, pause_on_request.run_if(gameplay_running))
+            .add_systems(
+                Update,
+                (navigate_pause_menu, update_pause_menu)
+                    .chain()
+                    .run_if(in_state(PauseState::Paused))
+                    // The settings screen on top has the input.
+                    .run_if(in_state(SettingsState::Closed)),
+            )
+            .add_systems(OnEnter(PauseState::Paused), (freeze, pause_menu_setup))
+            .add_systems(
+                OnExit(PauseState::Paused),
+                (unfreeze, ui::despawn_all::<PauseMenu>),
+            )
+            // However the run ends, it can't stay paused.
+            .add_systems(OnExit(GameState::Playing), resume)
+            .add_systems(OnEnter(GameState::Restarting), restart);
+    }
+}
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+enum PauseMenuItem {
+    Resume,
+    Settings,
+    Restart,
+    Quit,
+}
+
+impl PauseMenuItem {
+    const ALL: [PauseMenuItem; 4] = [
+        PauseMenuItem::Resume,
+        PauseMenuItem::Settings,
+        PauseMenuItem::Restart,
+        PauseMenuItem::Quit,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            PauseMenuItem::Resume => "Resume",
+            PauseMenuItem::Settings => "Settings",
+            PauseMenuItem::Restart => "Restart",
+            PauseMenuItem::Quit => "Quit to Splash",
+        }
+    }
+}
+
+/// Which item of the pause menu is highlighted.
+#[derive(Resource, Default)]
+struct PauseMenuSelection(usize);
+
+#[derive(Component)]
+struct PauseMenu;
+
+fn pause_on_request(
+    input: MenuInput,
+    mut focus: EventReader<WindowFocused>,
+    mut next_state: ResMut<NextState<PauseState>>,
+) {
+    let lost_focus = focus.iter().any(|event| !event.focused);
+    if lost_focus || input.pressed(KeyCode::Escape, GamepadButtonType::Start) {
+        next_state.set(PauseState::Paused);
+    }
+}
+
+fn freeze(mut time: ResMut<Time>, mut physics_loop: ResMut<PhysicsLoop>) {
+    time.pause();
+    physics_loop.pause();
+}
+
+fn unfreeze(mut time: ResMut<Time>, mut physics_loop: ResMut<PhysicsLoop>) {
+    time.unpause();
+    physics_loop.resume();
+}
+
+fn resume(mut next_state: ResMut<NextState<PauseState>>) {
+    next_state.set(PauseState::Running);
+}
+
+/// Passes straight back into [`GameState::Playing`], so the level gets torn
+/// down and built again.
+fn restart(mut next_state: ResMut<NextState<GameState>>) {
+    next_state.set(GameState::Playing);
+}
+
+fn pause_menu_setup(assets: Res<LDAssets>, mut commands: Commands) {
+    commands.insert_resource(PauseMenuSelection::default());
+    commands
+        .spawn((ui::menu_overlay(0.6, ZIndex::default()), PauseMenu))
+        .with_children(|menu| {
+            menu.spawn(TextBundle::from_section(
+                "Paused",
+                TextStyle {
+                    font: assets.font.clone(),
+                    font_size: 60.0,
+                    color: Color::WHITE,
+                },
+            ));
+            for item in PauseMenuItem::ALL {
+                menu.spawn((
+                    TextBundle::from_section(
+                        item.label(),
+                        TextStyle {
+                            font: assets.font.clone(),
+                            font_size: 30.0,
+                            color: Color::GRAY,
+                        },
+                    ),
+                    item,
+                ));
+            }
+        });
+}
+
+/// Up and down pick an item, space or enter picks it, and so does tapping it.
+/// Escape or Start again is the same as Resume.
+fn navigate_pause_menu(
+    input: MenuInput,
+    items: Query<(&PauseMenuItem, &Node, &GlobalTransform)>,
+    mut selection: ResMut<PauseMenuSelection>,
+    mut pause_state: ResMut<NextState<PauseState>>,
+    mut game_state: ResMut<NextState<GameState>>,
+    mut settings_state: ResMut<NextState<SettingsState>>,
+) {
+    if input.pressed(KeyCode::Escape, GamepadButtonType::Start) {
+        pause_state.set(PauseState::Running);
+        return;
+    }
+    if input.pressed(KeyCode::Up, GamepadButtonType::DPadUp) {
+        selection.0 = selection.0.saturating_sub(1);
+    }
+    if input.pressed(KeyCode::Down, GamepadButtonType::DPadDown) {
+        selection.0 = (selection.0 + 1).min(PauseMenuItem::ALL.len() - 1);
+    }
+    let tapped = items
+        .iter()
+        .find(|(_, node, transform)| input.tap_on(node, transform).is_some())
+        .and_then(|(item, _, _)| PauseMenuItem::ALL.iter().position(|other| other == item));
+    if let Some(index) = tapped {
+        selection.0 = index;
+    }
+    if input.confirmed() || tapped.is_some() {
+        match PauseMenuItem::ALL[selection.0] {
+            PauseMenuItem::Resume => pause_state.set(PauseState::Running),
+            PauseMenuItem::Settings => settings_state.set(SettingsState::Open),
+            PauseMenuItem::Restart => game_state.set(GameState::Restarting),
+            PauseMenuItem::Quit => game_state.set(GameState::Splash),
+        }
+    }
+}
+
+fn update_pause_menu(
+    selection: Res<PauseMenuSelection>,
+    mut items: Query<(&PauseMenuItem, &mut Text)>,
+) {
+    if !selection.is_changed() {
+        return;
+    }
+    let selected = PauseMenuItem::ALL[selection.0];
+    for (item, mut text) in &mut items {
+        text.sections[0].style.color = if *item == selected {
+            Color::YELLOW
+        } else {
+            Color::GRAY
+        };
+    }
+}
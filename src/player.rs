@@ -1,40 +1,124 @@
 use bevy::{prelude::*, sprite::Anchor};
 use bevy_xpbd_2d::prelude::*;
-use leafwing_input_manager::{axislike::VirtualAxis, prelude::*};
+use leafwing_input_manager::prelude::*;
 
-use crate::{animation::AnimationIndices, Action, LDAssets, Layer};
+use crate::{
+    animation::{AnimationCue, AnimationIndices, AnimationSheet},
+    Action, LDAssets, Layer, PLAYER_X_SPEED,
+};
 
+/// Faster than this and the player counts as moving that way.
+const MOVING: f32 = 0.2;
+
+/// The player's clips. Comes from the tags in `player.aseprite.json`; the
+/// default is only there until that's loaded, or for tags it's missing.
 #[derive(Resource)]
 pub(crate) struct PlayerAnimationTable {
     pub(crate) idle: AnimationIndices,
     pub(crate) walk: AnimationIndices,
     pub(crate) jump_up: AnimationIndices,
     pub(crate) jump_down: AnimationIndices,
+    pub(crate) land: AnimationIndices,
+    pub(crate) hurt: AnimationIndices,
+}
+
+impl PlayerAnimationTable {
+    /// Reads the `idle`, `walk`, `jump_up`, `jump_down`, `land` and `hurt`
+    /// tags. Any that are missing keep the built in clip.
+    pub(crate) fn from_sheet(sheet: &AnimationSheet) -> Self {
+        let built_in = PlayerAnimationTable::default();
+        let clip = |name, fallback: AnimationIndices| {
+            sheet.clip(name).unwrap_or_else(|e| {
+                warn!("Keeping the built in {} animation: {}", name, e);
+                fallback
+            })
+        };
+        PlayerAnimationTable {
+            idle: clip("idle", built_in.idle),
+            walk: clip("walk", built_in.walk),
+            jump_up: clip("jump_up", built_in.jump_up),
+            jump_down: clip("jump_down", built_in.jump_down),
+            land: clip("land", built_in.land),
+            hurt: clip("hurt", built_in.hurt),
+        }
+        .with_cues()
+    }
+
+    /// Every walk frame puts a foot down.
+    fn with_cues(mut self) -> Self {
+        self.walk = self.walk.with_cue_on_every_frame(AnimationCue::Footstep);
+        self
+    }
+
+    pub(crate) fn clip(&self, state: PlayerAnimationState) -> &AnimationIndices {
+        match state {
+            PlayerAnimationState::Idle => &self.idle,
+            PlayerAnimationState::Walk => &self.walk,
+            PlayerAnimationState::JumpUp => &self.jump_up,
+            PlayerAnimationState::JumpDown => &self.jump_down,
+            PlayerAnimationState::Land => &self.land,
+            PlayerAnimationState::Hurt => &self.hurt,
+        }
+    }
 }
 
 impl Default for PlayerAnimationTable {
     fn default() -> Self {
+        let clip = |first, last, seconds, mode| AnimationIndices {
+            first,
+            last,
+            timer: Timer::from_seconds(seconds, mode),
+            ..default()
+        };
         PlayerAnimationTable {
-            idle: AnimationIndices {
-                first: 0,
-                last: 0,
-                timer: Timer::from_seconds(0.03, TimerMode::Repeating),
-            },
-            walk: AnimationIndices {
-                first: 1,
-                last: 2,
-                timer: Timer::from_seconds(0.03, TimerMode::Repeating),
-            },
-            jump_up: AnimationIndices {
-                first: 4,
-                last: 5,
-                timer: Timer::from_seconds(0.03, TimerMode::Once),
-            },
-            jump_down: AnimationIndices {
-                first: 6,
-                last: 6,
-                timer: Timer::from_seconds(0.03, TimerMode::Once),
-            },
+            idle: clip(0, 0, 0.03, TimerMode::Repeating),
+            walk: clip(1, 2, 0.25, TimerMode::Repeating),
+            jump_up: clip(4, 5, 0.03, TimerMode::Once),
+            jump_down: clip(6, 6, 0.03, TimerMode::Once),
+            land: clip(3, 3, 0.1, TimerMode::Once),
+            hurt: clip(7, 8, 0.15, TimerMode::Once),
+        }
+        .with_cues()
+    }
+}
+
+/// What the player's sprite is doing. Only changes through
+/// [`PlayerAnimationState::next`].
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub(crate) enum PlayerAnimationState {
+    #[default]
+    Idle,
+    Walk,
+    JumpUp,
+    JumpDown,
+    /// Touching down after a fall.
+    Land,
+    /// Just lost a life.
+    Hurt,
+}
+
+impl PlayerAnimationState {
+    /// The transition rules. `hurt` is set on the frame the player loses a
+    /// life, `clip_finished` once a clip that plays once is over.
+    pub(crate) fn next(
+        self,
+        velocity: Vec2,
+        grounded: bool,
+        hurt: bool,
+        clip_finished: bool,
+    ) -> Self {
+        match self {
+            _ if hurt => PlayerAnimationState::Hurt,
+            // Hurt plays out no matter what, landing unless they jump again.
+            PlayerAnimationState::Hurt if !clip_finished => PlayerAnimationState::Hurt,
+            PlayerAnimationState::Land if !clip_finished && grounded => PlayerAnimationState::Land,
+            PlayerAnimationState::JumpUp | PlayerAnimationState::JumpDown if grounded => {
+                PlayerAnimationState::Land
+            }
+            _ if !grounded && velocity.y < 0.0 => PlayerAnimationState::JumpDown,
+            _ if !grounded => PlayerAnimationState::JumpUp,
+            _ if velocity.x.abs() > MOVING => PlayerAnimationState::Walk,
+            _ => PlayerAnimationState::Idle,
         }
     }
 }
@@ -42,6 +126,101 @@ impl Default for PlayerAnimationTable {
 #[derive(Component, Default)]
 pub(crate) struct Player;
 
+/// How the player runs and jumps. Speeds are in pixels per second,
+/// accelerations in pixels per second per second.
+#[derive(Resource, Clone, Debug)]
+pub(crate) struct PlayerMovementConfig {
+    /// Top running speed, with the stick or a key all the way over.
+    pub(crate) max_speed: f32,
+    /// Speeding up on the ground.
+    pub(crate) ground_acceleration: f32,
+    /// Slowing down or turning around on the ground.
+    pub(crate) ground_deceleration: f32,
+    pub(crate) air_acceleration: f32,
+    pub(crate) air_deceleration: f32,
+    /// Upward speed at the start of a jump.
+    pub(crate) jump_speed: f32,
+    /// Letting go of jump while still rising multiplies the upward speed by
+    /// this, so a tap is a hop and holding is a full jump.
+    pub(crate) jump_cut: f32,
+    /// Jump still works this long after walking off a ledge.
+    pub(crate) coyote_time: f32,
+    /// A jump pressed this long before landing still happens when they land.
+    pub(crate) jump_buffer_time: f32,
+}
+
+impl Default for PlayerMovementConfig {
+    fn default() -> Self {
+        PlayerMovementConfig {
+            max_speed: PLAYER_X_SPEED,
+            ground_acceleration: 2400.0,
+            ground_deceleration: 3000.0,
+            air_acceleration: 1200.0,
+            air_deceleration: 800.0,
+            jump_speed: 400.0,
+            jump_cut: 0.5,
+            coyote_time: 0.1,
+            jump_buffer_time: 0.12,
+        }
+    }
+}
+
+impl PlayerMovementConfig {
+    /// Moves the horizontal speed towards `target` for one frame. Speeding up
+    /// uses the acceleration, slowing down and turning the deceleration.
+    pub(crate) fn run(&self, current: f32, target: f32, grounded: bool, delta: f32) -> f32 {
+        let speeding_up = target.abs() > current.abs() && target * current >= 0.0;
+        let rate = match (grounded, speeding_up) {
+            (true, true) => self.ground_acceleration,
+            (true, false) => self.ground_deceleration,
+            (false, true) => self.air_acceleration,
+            (false, false) => self.air_deceleration,
+        };
+        let step = rate * delta;
+        current + (target - current).clamp(-step, step)
+    }
+}
+
+/// How far below the player's feet still counts as standing on something.
+const GROUND_DISTANCE: f32 = 4.0;
+
+/// Whether the player has something to jump off, and how long ago they last
+/// did and last asked to jump. Kept by `update_footing` from the player's
+/// [`ShapeHits`].
+#[derive(Component)]
+pub(crate) struct Footing {
+    pub(crate) grounded: bool,
+    /// Seconds since the player was last grounded.
+    pub(crate) since_grounded: f32,
+    /// Seconds since jump was last pressed.
+    pub(crate) since_jump_pressed: f32,
+}
+
+impl Default for Footing {
+    fn default() -> Self {
+        Footing {
+            grounded: false,
+            since_grounded: f32::INFINITY,
+            since_jump_pressed: f32::INFINITY,
+        }
+    }
+}
+
+/// Casts a slightly thinner copy of the player's capsule down, so walls
+/// they're pressed against don't count as ground.
+fn ground_caster() -> ShapeCaster {
+    ShapeCaster::new(
+        Collider::capsule_endpoints(Vec2::new(-3.0, 0.0), Vec2::new(8.0, 0.0), 20.0),
+        Vec2::ZERO,
+        0.0,
+        Vec2::NEG_Y,
+    )
+    .with_max_time_of_impact(GROUND_DISTANCE)
+    .with_query_filter(
+        SpatialQueryFilter::new().with_masks([Layer::Rock, Layer::Wall, Layer::Paddle]),
+    )
+}
+
 #[derive(Bundle)]
 pub(crate) struct PlayerBundle {
     player: Player,
@@ -50,6 +229,9 @@ pub(crate) struct PlayerBundle {
     #[bundle()]
     sprite: SpriteSheetBundle,
     animation_indices: AnimationIndices,
+    animation_state: PlayerAnimationState,
+    footing: Footing,
+    ground_caster: ShapeCaster,
     collider: Collider,
     rigid_body: RigidBody,
     external_force: ExternalForce,
@@ -60,29 +242,24 @@ pub(crate) struct PlayerBundle {
     sleeping_disabled: SleepingDisabled,
 }
 
-fn player_input_map() -> InputMap<Action> {
-    let mut input_map = InputMap::default();
-    input_map.insert(
-        UserInput::VirtualAxis(VirtualAxis {
-            negative: KeyCode::Left.into(),
-            positive: KeyCode::Right.into(),
-        }),
-        Action::Move,
-    );
-    input_map.insert(
-        UserInput::VirtualAxis(VirtualAxis {
-            negative: GamepadButtonType::DPadLeft.into(),
-            positive: GamepadButtonType::DPadRight.into(),
-        }),
-        Action::Move,
-    );
-    input_map.insert(KeyCode::Up, Action::Jump);
-    input_map.insert(GamepadButtonType::South, Action::Jump);
-    input_map
+/// What the player bumps into. Balls pass through them unless
+/// `hit_by_balls`.
+pub(crate) fn player_collision_layers(hit_by_balls: bool) -> CollisionLayers {
+    let layers = CollisionLayers::new([Layer::Player], [Layer::Rock, Layer::Wall, Layer::Paddle]);
+    if hit_by_balls {
+        layers.add_mask(Layer::Ball)
+    } else {
+        layers
+    }
 }
 
 impl PlayerBundle {
-    pub(crate) fn new(assets: &LDAssets, animations: &PlayerAnimationTable) -> Self {
+    pub(crate) fn new(
+        assets: &LDAssets,
+        animations: &PlayerAnimationTable,
+        input_map: InputMap<Action>,
+        position: Vec3,
+    ) -> Self {
         let idle_player = animations.idle.clone();
 
         PlayerBundle {
@@ -93,27 +270,33 @@ impl PlayerBundle {
                     anchor: Anchor::Custom(Vec2::new(-0.1, -0.2)),
                     ..default()
                 },
-                transform: Transform::from_translation(Vec3::new(0.0, 100.0, 1.0)),
+                transform: Transform::from_translation(position),
                 ..default()
             },
             input_manager: InputManagerBundle::<Action> {
-                input_map: player_input_map(),
+                input_map,
                 ..default()
             },
             animation_indices: idle_player,
+            animation_state: PlayerAnimationState::Idle,
+            footing: Footing::default(),
+            ground_caster: ground_caster(),
             player: Player,
             rigid_body: RigidBody::Dynamic,
             collider: Collider::capsule_endpoints(Vec2::new(-5.0, 0.0), Vec2::new(10.0, 0.0), 21.0),
             external_force: ExternalForce::ZERO,
             locked_axes: LockedAxes::new().lock_rotation(),
             gravity_scale: GravityScale(1.0),
-            collision_layer: CollisionLayers::new(
-                [Layer::Player],
-                [Layer::Rock, Layer::Wall, Layer::Paddle, Layer::Ball],
-            ),
+            collision_layer: player_collision_layers(true),
             restitution: Restitution::PERFECTLY_INELASTIC
                 .with_combine_rule(CoefficientCombine::Min),
             sleeping_disabled: SleepingDisabled,
         }
     }
+
+    /// Only listen to this gamepad, so a second one can drive the paddle.
+    pub(crate) fn with_gamepad(mut self, gamepad: Gamepad) -> Self {
+        self.input_manager.input_map.set_gamepad(gamepad);
+        self
+    }
 }
@@ -0,0 +1,50 @@
+use bevy::prelude::*;
+use rand::prelude::*;
+
+/// Every subsystem that needs randomness pulls from its own stream so that
+/// adding a roll in one place doesn't shift the numbers seen by another.
+#[derive(Resource)]
+pub(crate) struct Randomizer {
+    /// The seed every stream was derived from. Shown on the game over screen
+    /// so runs can be reproduced.
+    pub(crate) seed: u64,
+    /// Rock layout in `spawn_rocks`.
+    pub(crate) rocks: SmallRng,
+    /// Ball launch angles.
+    pub(crate) balls: SmallRng,
+    /// Sound effect choices.
+    pub(crate) sfx: SmallRng,
+    /// How far off the paddle aims.
+    pub(crate) paddle: SmallRng,
+}
+
+/// Seeds stream number `stream` from the run's seed. Adding the stream number
+/// would make seed N's second stream seed N+1's first, so it's spread out
+/// with the golden ratio constant and mixed in instead.
+fn stream(seed: u64, stream: u64) -> SmallRng {
+    SmallRng::seed_from_u64(seed ^ stream.wrapping_mul(0x9E37_79B9_7F4A_7C15))
+}
+
+impl Randomizer {
+    pub(crate) fn new(seed: u64) -> Self {
+        Randomizer {
+            seed,
+            rocks: stream(seed, 0),
+            balls: stream(seed, 1),
+            sfx: stream(seed, 2),
+            paddle: stream(seed, 3),
+        }
+    }
+
+    /// Uses the seed from the options if one was given, otherwise rolls a
+    /// fresh one.
+    pub(crate) fn from_seed_option(seed: Option<u64>) -> Self {
+        Randomizer::new(seed.unwrap_or_else(|| SmallRng::from_entropy().gen()))
+    }
+}
+
+impl Default for Randomizer {
+    fn default() -> Self {
+        Randomizer::from_seed_option(None)
+    }
+}
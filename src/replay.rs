@@ -0,0 +1,424 @@
+use std::time::Duration;
+
+use bevy::{ecs::query::Has, prelude::*, time::TimeUpdateStrategy};
+use bevy_xpbd_2d::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use crate::campaign::Campaign;
+use crate::controls::Controls;
+use crate::difficulty::Difficulty;
+use crate::{player, random::Randomizer, Action, GameMode, GameOptions};
+
+/// Bumped whenever the byte layout of a [`Recording`] changes.
+const REPLAY_VERSION: u8 = 3;
+const REPLAY_MAGIC: &[u8; 4] = b"LD54";
+const HEADER_SIZE: usize = 4 + 1 + 8 + 4 + 1 + 4 + 4;
+const FRAME_SIZE: usize = 4 + 4 + 1;
+
+const MOVE_PRESSED: u8 = 1 << 0;
+const JUMP_PRESSED: u8 = 1 << 1;
+
+/// What the player was doing during a single `Update` while playing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct InputFrame {
+    pub(crate) delta: Duration,
+    pub(crate) move_pressed: bool,
+    pub(crate) move_value: f32,
+    pub(crate) jump_pressed: bool,
+}
+
+/// A whole run worth of input. Together with the settings it was played with
+/// this is enough to play the run back exactly.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Recording {
+    pub(crate) seed: u64,
+    /// Index into the campaign.
+    pub(crate) level: usize,
+    pub(crate) difficulty: Difficulty,
+    pub(crate) lives: u32,
+    pub(crate) frames: Vec<InputFrame>,
+}
+
+impl Recording {
+    /// Packs the recording into a little endian blob: a short header followed
+    /// by nine bytes per frame.
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + self.frames.len() * FRAME_SIZE);
+        bytes.extend_from_slice(REPLAY_MAGIC);
+        bytes.push(REPLAY_VERSION);
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.extend_from_slice(&(self.level as u32).to_le_bytes());
+        bytes.push(difficulty_index(self.difficulty));
+        bytes.extend_from_slice(&self.lives.to_le_bytes());
+        bytes.extend_from_slice(&(self.frames.len() as u32).to_le_bytes());
+        for frame in &self.frames {
+            // Nobody is getting a four second frame and still playing.
+            let nanos = u32::try_from(frame.delta.as_nanos()).unwrap_or(u32::MAX);
+            bytes.extend_from_slice(&nanos.to_le_bytes());
+            bytes.extend_from_slice(&frame.move_value.to_le_bytes());
+            let mut flags = 0;
+            if frame.move_pressed {
+                flags |= MOVE_PRESSED;
+            }
+            if frame.jump_pressed {
+                flags |= JUMP_PRESSED;
+            }
+            bytes.push(flags);
+        }
+        bytes
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<Recording, String> {
+        if bytes.len() < HEADER_SIZE || &bytes[0..4] != REPLAY_MAGIC {
+            return Err("Not a replay file".to_owned());
+        }
+        if bytes[4] != REPLAY_VERSION {
+            return Err(format!("Unsupported replay version {}", bytes[4]));
+        }
+        let seed = u64::from_le_bytes(bytes[5..13].try_into().unwrap());
+        let level = u32::from_le_bytes(bytes[13..17].try_into().unwrap()) as usize;
+        let difficulty = *Difficulty::ALL
+            .get(usize::from(bytes[17]))
+            .ok_or_else(|| format!("Unknown difficulty {}", bytes[17]))?;
+        let lives = u32::from_le_bytes(bytes[18..22].try_into().unwrap());
+        let count = u32::from_le_bytes(bytes[22..26].try_into().unwrap()) as usize;
+        let body = &bytes[HEADER_SIZE..];
+        if body.len() != count * FRAME_SIZE {
+            return Err(format!(
+                "Replay should have {} frames but has {} bytes of them",
+                count,
+                body.len()
+            ));
+        }
+
+        let frames = body
+            .chunks_exact(FRAME_SIZE)
+            .map(|chunk| InputFrame {
+                delta: Duration::from_nanos(u64::from(u32::from_le_bytes(
+                    chunk[0..4].try_into().unwrap(),
+                ))),
+                move_value: f32::from_le_bytes(chunk[4..8].try_into().unwrap()),
+                move_pressed: chunk[8] & MOVE_PRESSED != 0,
+                jump_pressed: chunk[8] & JUMP_PRESSED != 0,
+            })
+            .collect();
+
+        Ok(Recording {
+            seed,
+            level,
+            difficulty,
+            lives,
+            frames,
+        })
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn load(path: &str) -> Result<Recording, String> {
+        let bytes = std::fs::read(path).map_err(|e| format!("Couldn't read {path}: {e}"))?;
+        Recording::from_bytes(&bytes)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save(&self, path: &str) -> Result<(), String> {
+        std::fs::write(path, self.to_bytes()).map_err(|e| format!("Couldn't write {path}: {e}"))
+    }
+
+    /// Hands the recording to the browser as a file download.
+    #[cfg(target_arch = "wasm32")]
+    fn save(&self, name: &str) -> Result<(), String> {
+        download(name, &self.to_bytes()).map_err(|e| format!("Couldn't download {name}: {e:?}"))
+    }
+}
+
+fn difficulty_index(difficulty: Difficulty) -> u8 {
+    Difficulty::ALL
+        .iter()
+        .position(|&other| other == difficulty)
+        .unwrap_or_default() as u8
+}
+
+#[cfg(target_arch = "wasm32")]
+fn download(name: &str, bytes: &[u8]) -> Result<(), wasm_bindgen::JsValue> {
+    use wasm_bindgen::JsCast;
+
+    let parts = js_sys::Array::new();
+    parts.push(&js_sys::Uint8Array::from(bytes));
+    let blob = web_sys::Blob::new_with_u8_array_sequence(&parts)?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob)?;
+    let document = web_sys::window()
+        .and_then(|w| w.document())
+        .ok_or("No document?")?;
+    let anchor: web_sys::HtmlAnchorElement = document.create_element("a")?.dyn_into()?;
+    anchor.set_href(&url);
+    anchor.set_download(name);
+    anchor.click();
+    web_sys::Url::revoke_object_url(&url)
+}
+
+enum ReplayMode {
+    Off,
+    /// Capture every frame and write it to `target` when the run ends.
+    Record { target: String },
+    /// Feed frames back instead of reading the input map.
+    Playback { cursor: usize },
+}
+
+/// Records the player's [`ActionState`] or plays one back, depending on the
+/// `record`/`replay` options.
+#[derive(Resource)]
+pub(crate) struct InputReplay {
+    mode: ReplayMode,
+    recording: Recording,
+}
+
+impl Default for InputReplay {
+    fn default() -> Self {
+        InputReplay {
+            mode: ReplayMode::Off,
+            recording: Recording::default(),
+        }
+    }
+}
+
+impl InputReplay {
+    pub(crate) fn record(target: String) -> Self {
+        InputReplay {
+            mode: ReplayMode::Record { target },
+            recording: Recording::default(),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn playback(recording: Recording) -> Self {
+        InputReplay {
+            mode: ReplayMode::Playback { cursor: 0 },
+            recording,
+        }
+    }
+
+    /// The clock for a frame is read before anything in `Update` runs, so the
+    /// playback has to queue up the delta one frame ahead.
+    pub(crate) fn time_strategy(&self) -> TimeUpdateStrategy {
+        match self.mode {
+            ReplayMode::Playback { cursor } => self
+                .recording
+                .frames
+                .get(cursor)
+                .map_or(TimeUpdateStrategy::Automatic, |f| {
+                    TimeUpdateStrategy::ManualDuration(f.delta)
+                }),
+            _ => TimeUpdateStrategy::Automatic,
+        }
+    }
+}
+
+pub(crate) fn start_replay(
+    mut replay: ResMut<InputReplay>,
+    rng: Res<Randomizer>,
+    campaign: Res<Campaign>,
+    difficulty: Res<Difficulty>,
+    config: Res<GameOptions>,
+    mut physics_loop: ResMut<PhysicsLoop>,
+) {
+    let replay = replay.as_mut();
+    if !matches!(replay.mode, ReplayMode::Off) {
+        // Whatever time the physics had left over from the menus isn't in the
+        // recording, so both runs have to start stepping from zero.
+        *physics_loop = PhysicsLoop::default();
+    }
+    match &mut replay.mode {
+        ReplayMode::Off => {}
+        ReplayMode::Record { .. } => {
+            replay.recording = Recording {
+                seed: rng.seed,
+                level: campaign.level,
+                difficulty: *difficulty,
+                lives: config.lives,
+                frames: Vec::new(),
+            };
+        }
+        ReplayMode::Playback { cursor } => {
+            if rng.seed != replay.recording.seed {
+                warn!(
+                    "Replay was recorded with seed {} but the run uses {}",
+                    replay.recording.seed, rng.seed
+                );
+            }
+            if campaign.level != replay.recording.level {
+                warn!(
+                    "Replay was recorded on level {} but the run is on {}",
+                    replay.recording.level, campaign.level
+                );
+            }
+            if *difficulty != replay.recording.difficulty {
+                warn!(
+                    "Replay was recorded on {} but the run is on {}",
+                    replay.recording.difficulty.name(),
+                    difficulty.name()
+                );
+            }
+            *cursor = 0;
+        }
+    }
+}
+
+pub(crate) fn replay_inputs(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut replay: ResMut<InputReplay>,
+    mut time_strategy: ResMut<TimeUpdateStrategy>,
+    controls: Res<Controls>,
+    mode: Res<GameMode>,
+    mut player_query: Query<
+        (Entity, &mut ActionState<Action>, Has<InputMap<Action>>),
+        With<player::Player>,
+    >,
+) {
+    let Ok((player, mut action_state, has_input_map)) = player_query.get_single_mut() else {
+        return;
+    };
+    let replay = replay.as_mut();
+
+    match &mut replay.mode {
+        ReplayMode::Off => {}
+        ReplayMode::Record { .. } => {
+            replay.recording.frames.push(InputFrame {
+                delta: time.delta(),
+                move_pressed: action_state.pressed(Action::Move),
+                move_value: action_state.value(Action::Move),
+                jump_pressed: action_state.pressed(Action::Jump),
+            });
+        }
+        ReplayMode::Playback { cursor } => {
+            let Some(frame) = replay.recording.frames.get(*cursor).copied() else {
+                info!("Replay finished, handing control back");
+                commands.entity(player).insert(controls.input_map(*mode));
+                replay.mode = ReplayMode::Off;
+                *time_strategy = TimeUpdateStrategy::Automatic;
+                return;
+            };
+            // Without the input map leafwing leaves the action state alone, so
+            // press and release keep their usual just-pressed semantics.
+            if has_input_map {
+                commands.entity(player).remove::<InputMap<Action>>();
+            }
+            if frame.move_pressed {
+                action_state.press(Action::Move);
+            } else {
+                action_state.release(Action::Move);
+            }
+            action_state.action_data_mut(Action::Move).value = frame.move_value;
+            if frame.jump_pressed {
+                action_state.press(Action::Jump);
+            } else {
+                action_state.release(Action::Jump);
+            }
+            *cursor += 1;
+            *time_strategy = replay.time_strategy();
+        }
+    }
+}
+
+pub(crate) fn finish_replay(
+    mut replay: ResMut<InputReplay>,
+    mut time_strategy: ResMut<TimeUpdateStrategy>,
+) {
+    let replay = replay.as_mut();
+    match &replay.mode {
+        ReplayMode::Off => {}
+        ReplayMode::Record { target } => {
+            match replay.recording.save(target) {
+                Ok(()) => info!(
+                    "Saved {} frames of level {} with seed {} to {}",
+                    replay.recording.frames.len(),
+                    replay.recording.level,
+                    replay.recording.seed,
+                    target
+                ),
+                Err(e) => error!("{}", e),
+            }
+        }
+        ReplayMode::Playback { .. } => {
+            replay.mode = ReplayMode::Off;
+            *time_strategy = TimeUpdateStrategy::Automatic;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recording() -> Recording {
+        Recording {
+            seed: 0xDEAD_BEEF_1234,
+            level: 2,
+            difficulty: Difficulty::Hard,
+            lives: 5,
+            frames: vec![
+                InputFrame {
+                    delta: Duration::from_nanos(16_666_667),
+                    move_value: -0.5,
+                    move_pressed: true,
+                    jump_pressed: false,
+                },
+                InputFrame {
+                    delta: Duration::from_nanos(16_666_666),
+                    move_value: 0.0,
+                    move_pressed: false,
+                    jump_pressed: true,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn round_trips() {
+        let recording = recording();
+        assert_eq!(Recording::from_bytes(&recording.to_bytes()), Ok(recording));
+    }
+
+    #[test]
+    fn round_trips_empty() {
+        let recording = Recording::default();
+        assert_eq!(Recording::from_bytes(&recording.to_bytes()), Ok(recording));
+    }
+
+    #[test]
+    fn rejects_truncated() {
+        let bytes = recording().to_bytes();
+        assert!(Recording::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Recording::from_bytes(&bytes[..HEADER_SIZE - 1]).is_err());
+    }
+
+    #[test]
+    fn rejects_bad_magic() {
+        let mut bytes = recording().to_bytes();
+        bytes[0] = b'X';
+        assert_eq!(
+            Recording::from_bytes(&bytes),
+            Err("Not a replay file".to_owned())
+        );
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let mut bytes = recording().to_bytes();
+        bytes[4] = REPLAY_VERSION + 1;
+        assert_eq!(
+            Recording::from_bytes(&bytes),
+            Err(format!("Unsupported replay version {}", REPLAY_VERSION + 1))
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_difficulty() {
+        let mut bytes = recording().to_bytes();
+        bytes[17] = 9;
+        assert_eq!(
+            Recording::from_bytes(&bytes),
+            Err("Unknown difficulty 9".to_owned())
+        );
+    }
+}
@@ -0,0 +1,84 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::difficulty::Difficulty;
+use crate::storage;
+
+/// How many runs make the table.
+const HIGH_SCORE_COUNT: usize = 10;
+const STORAGE_KEY: &str = "highscores";
+
+/// One run that made the table.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct ScoreEntry {
+    /// Seconds survived.
+    pub(crate) time: f32,
+    /// When the run ended, in seconds since the Unix epoch.
+    pub(crate) timestamp: u64,
+    pub(crate) seed: u64,
+    pub(crate) difficulty: Difficulty,
+}
+
+impl ScoreEntry {
+    /// The day the run ended as `YYYY-MM-DD`, in UTC.
+    pub(crate) fn date(&self) -> String {
+        // Howard Hinnant's days-to-civil, shifted so years start in March.
+        let days = (self.timestamp / 86_400) as i64 + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        };
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+        format!("{year:04}-{month:02}-{day:02}")
+    }
+}
+
+/// The best survival times on this machine, longest first.
+#[derive(Resource, Default)]
+pub(crate) struct HighScores {
+    pub(crate) entries: Vec<ScoreEntry>,
+    /// Where the last run landed in [`HighScores::entries`], if it made it.
+    pub(crate) latest: Option<usize>,
+}
+
+impl HighScores {
+    /// Reads the saved table. A missing or broken one starts fresh.
+    pub(crate) fn load() -> Self {
+        let entries = storage::load_ron(STORAGE_KEY, "high scores");
+        HighScores {
+            entries,
+            latest: None,
+        }
+    }
+
+    /// Adds a run if it's good enough and saves the table.
+    pub(crate) fn record(&mut self, entry: ScoreEntry) {
+        let rank = self
+            .entries
+            .iter()
+            .position(|other| entry.time > other.time)
+            .unwrap_or(self.entries.len());
+        if rank >= HIGH_SCORE_COUNT {
+            self.latest = None;
+            return;
+        }
+
+        self.entries.insert(rank, entry);
+        self.entries.truncate(HIGH_SCORE_COUNT);
+        self.latest = Some(rank);
+        storage::save_ron(STORAGE_KEY, &self.entries, "high scores");
+    }
+
+    /// Whether the last run beat everything else on the table.
+    pub(crate) fn is_new_record(&self) -> bool {
+        self.latest == Some(0)
+    }
+}
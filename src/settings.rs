@@ -0,0 +1,277 @@
+use bevy::{prelude::*, window::WindowMode};
+use serde::{Deserialize, Serialize};
+
+use crate::ui::{self, MenuInput};
+use crate::{storage, LDAssets};
+
+const STORAGE_KEY: &str = "settings";
+/// How far left/right moves a volume slider.
+const VOLUME_STEP: f32 = 0.1;
+
+/// Player preferences that stick around between runs.
+#[derive(Resource, Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub(crate) struct Settings {
+    /// 0 to 1.
+    pub(crate) music_volume: f32,
+    /// 0 to 1.
+    pub(crate) sfx_volume: f32,
+    /// Play effects in the middle instead of panning them to where they
+    /// happened, for players who only hear out of one side.
+    pub(crate) mono: bool,
+    pub(crate) fullscreen: bool,
+    /// Draw colliders, contacts and the paddle's predictions.
+    pub(crate) debug: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            // Music is scaled by the master volume, which is already down
+            // at 0.3.
+            music_volume: 1.0,
+            sfx_volume: 1.0,
+            mono: false,
+            fullscreen: false,
+            debug: false,
+        }
+    }
+}
+
+impl Settings {
+    /// Reads the saved settings. Missing or broken ones fall back to the
+    /// defaults, and hand-edited volumes are pulled back into range.
+    pub(crate) fn load() -> Self {
+        let mut settings: Settings = storage::load_ron(STORAGE_KEY, "settings");
+        settings.music_volume = settings.music_volume.clamp(0.0, 1.0);
+        settings.sfx_volume = settings.sfx_volume.clamp(0.0, 1.0);
+        settings
+    }
+
+    pub(crate) fn window_mode(&self) -> WindowMode {
+        if self.fullscreen {
+            WindowMode::BorderlessFullscreen
+        } else {
+            WindowMode::Windowed
+        }
+    }
+}
+
+/// Whether the settings screen is up. It sits on top of whatever opened it,
+/// the splash screen or the pause menu, which stay where they were.
+#[derive(States, Default, Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub(crate) enum SettingsState {
+    #[default]
+    Closed,
+    Open,
+    /// The controls screen, which goes back to `Open`.
+    Controls,
+}
+
+/// The settings screen, and applying [`Settings`] to the window.
+///
+/// Requires [`LDAssets`] and [`Settings`]. The audio and debug plugins pick
+/// up their own settings.
+pub(crate) struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state::<SettingsState>()
+            .add_systems(OnEnter(SettingsState::Open), settings_menu_setup)
+            .add_systems(
+                OnExit(SettingsState::Open),
+                (save_settings, ui::despawn_all::<SettingsMenu>),
+            )
+            .add_systems(
+                Update,
+                (navigate_settings_menu, update_settings_menu)
+                    .chain()
+                    .run_if(in_state(SettingsState::Open)),
+            )
+            .add_systems(
+                Update,
+                apply_window_mode.run_if(resource_changed::<Settings>()),
+            );
+    }
+}
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+enum SettingsItem {
+    Music,
+    Sfx,
+    Mono,
+    Fullscreen,
+    Debug,
+    Controls,
+    Back,
+}
+
+impl SettingsItem {
+    const ALL: [SettingsItem; 7] = [
+        SettingsItem::Music,
+        SettingsItem::Sfx,
+        SettingsItem::Mono,
+        SettingsItem::Fullscreen,
+        SettingsItem::Debug,
+        SettingsItem::Controls,
+        SettingsItem::Back,
+    ];
+
+    fn label(self, settings: &Settings) -> String {
+        let slider = |volume: f32| {
+            let filled = (volume * 10.0).round() as usize;
+            format!(
+                "[{}{}] {:>3}%",
+                "#".repeat(filled),
+                "-".repeat(10 - filled),
+                (volume * 100.0).round()
+            )
+        };
+        let toggle = |on: bool| if on { "On" } else { "Off" };
+        match self {
+            SettingsItem::Music => format!("Music   {}", slider(settings.music_volume)),
+            SettingsItem::Sfx => format!("Effects {}", slider(settings.sfx_volume)),
+            SettingsItem::Mono => format!("Mono audio: {}", toggle(settings.mono)),
+            SettingsItem::Fullscreen => format!("Fullscreen: {}", toggle(settings.fullscreen)),
+            SettingsItem::Debug => format!("Debug: {}", toggle(settings.debug)),
+            SettingsItem::Controls => "Controls".to_owned(),
+            SettingsItem::Back => "Back".to_owned(),
+        }
+    }
+
+    /// Left and right turn sliders down and up, and flip toggles either way.
+    fn adjust(self, settings: &mut Settings, up: bool) {
+        let step = if up { VOLUME_STEP } else { -VOLUME_STEP };
+        match self {
+            SettingsItem::Music => {
+                settings.music_volume = (settings.music_volume + step).clamp(0.0, 1.0);
+            }
+            SettingsItem::Sfx => {
+                settings.sfx_volume = (settings.sfx_volume + step).clamp(0.0, 1.0);
+            }
+            SettingsItem::Mono => settings.mono = !settings.mono,
+            SettingsItem::Fullscreen => settings.fullscreen = !settings.fullscreen,
+            SettingsItem::Debug => settings.debug = !settings.debug,
+            SettingsItem::Controls | SettingsItem::Back => {}
+        }
+    }
+}
+
+/// Which row of the settings screen is highlighted.
+#[derive(Resource, Default)]
+struct SettingsSelection(usize);
+
+#[derive(Component)]
+struct SettingsMenu;
+
+fn settings_menu_setup(assets: Res<LDAssets>, settings: Res<Settings>, mut commands: Commands) {
+    commands.insert_resource(SettingsSelection::default());
+    commands
+        // In front of the pause menu.
+        .spawn((ui::menu_overlay(0.8, ZIndex::Global(1)), SettingsMenu))
+        .with_children(|menu| {
+            menu.spawn(TextBundle::from_section(
+                "Settings",
+                TextStyle {
+                    font: assets.font.clone(),
+                    font_size: 60.0,
+                    color: Color::WHITE,
+                },
+            ));
+            for item in SettingsItem::ALL {
+                menu.spawn((
+                    TextBundle::from_section(
+                        item.label(&settings),
+                        TextStyle {
+                            font: assets.font.clone(),
+                            font_size: 30.0,
+                            color: Color::GRAY,
+                        },
+                    ),
+                    item,
+                ));
+            }
+        });
+}
+
+fn save_settings(settings: Res<Settings>) {
+    storage::save_ron(STORAGE_KEY, &*settings, "settings");
+}
+
+/// Up and down pick a row, left and right change it. Escape, or space/enter
+/// on Back, closes the screen. Tapping a row picks it, and tapping either half
+/// of a slider turns it down or up.
+fn navigate_settings_menu(
+    input: MenuInput,
+    items: Query<(&SettingsItem, &Node, &GlobalTransform)>,
+    mut selection: ResMut<SettingsSelection>,
+    mut settings: ResMut<Settings>,
+    mut next_state: ResMut<NextState<SettingsState>>,
+) {
+    let tapped = items.iter().find_map(|(item, node, transform)| {
+        let position = input.tap_on(node, transform)?;
+        let index = SettingsItem::ALL.iter().position(|other| other == item)?;
+        Some((index, position.x >= transform.translation().x))
+    });
+    if let Some((index, right_half)) = tapped {
+        selection.0 = index;
+        let selected = SettingsItem::ALL[index];
+        match selected {
+            SettingsItem::Music | SettingsItem::Sfx => selected.adjust(&mut settings, right_half),
+            SettingsItem::Controls => next_state.set(SettingsState::Controls),
+            SettingsItem::Back => next_state.set(SettingsState::Closed),
+            _ => selected.adjust(&mut settings, true),
+        }
+        return;
+    }
+    let selected = SettingsItem::ALL[selection.0];
+
+    if input.pressed(KeyCode::Escape, GamepadButtonType::East) {
+        next_state.set(SettingsState::Closed);
+        return;
+    }
+    if input.pressed(KeyCode::Up, GamepadButtonType::DPadUp) {
+        selection.0 = selection.0.saturating_sub(1);
+    }
+    if input.pressed(KeyCode::Down, GamepadButtonType::DPadDown) {
+        selection.0 = (selection.0 + 1).min(SettingsItem::ALL.len() - 1);
+    }
+    if input.pressed(KeyCode::Left, GamepadButtonType::DPadLeft) {
+        selected.adjust(&mut settings, false);
+    }
+    if input.pressed(KeyCode::Right, GamepadButtonType::DPadRight) {
+        selected.adjust(&mut settings, true);
+    }
+    if input.confirmed() {
+        match selected {
+            SettingsItem::Controls => next_state.set(SettingsState::Controls),
+            SettingsItem::Back => next_state.set(SettingsState::Closed),
+            _ => selected.adjust(&mut settings, true),
+        }
+    }
+}
+
+fn update_settings_menu(
+    selection: Res<SettingsSelection>,
+    settings: Res<Settings>,
+    mut items: Query<(&SettingsItem, &mut Text)>,
+) {
+    if !selection.is_changed() && !settings.is_changed() {
+        return;
+    }
+    let selected = SettingsItem::ALL[selection.0];
+    for (item, mut text) in &mut items {
+        text.sections[0].value = item.label(&settings);
+        text.sections[0].style.color = if *item == selected {
+            Color::YELLOW
+        } else {
+            Color::GRAY
+        };
+    }
+}
+
+fn apply_window_mode(settings: Res<Settings>, mut windows: Query<&mut Window>) {
+    for mut window in &mut windows {
+        window.mode = settings.window_mode();
+    }
+}
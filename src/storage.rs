@@ -0,0 +1,83 @@
+//! Small text files that should outlive the game: high scores, settings.
+//! Natively they go in the user's data directory, in the browser they go in
+//! `localStorage`.
+
+use bevy::prelude::warn;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Reads the RON saved under `key`. Missing or broken files fall back to the
+/// default. `what` names it in the warning.
+pub(crate) fn load_ron<T: DeserializeOwned + Default>(key: &str, what: &str) -> T {
+    load(key)
+        .and_then(|text| match ron::from_str(&text) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                warn!("Ignoring broken {}: {}", what, e);
+                None
+            }
+        })
+        .unwrap_or_default()
+}
+
+/// Saves `value` as RON under `key`. A failed save only costs a warning.
+pub(crate) fn save_ron<T: Serialize>(key: &str, value: &T, what: &str) {
+    let saved = ron::to_string(value)
+        .map_err(|e| e.to_string())
+        .and_then(|text| save(key, &text));
+    if let Err(e) = saved {
+        warn!("Couldn't save {}: {}", what, e);
+    }
+}
+
+/// Reads what was last saved under `key`, if anything.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn load(key: &str) -> Option<String> {
+    std::fs::read_to_string(path(key)?).ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn save(key: &str, contents: &str) -> Result<(), String> {
+    let path = path(key).ok_or("No data directory")?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Couldn't create {}: {}", parent.display(), e))?;
+    }
+    std::fs::write(&path, contents).map_err(|e| format!("Couldn't write {}: {}", path.display(), e))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn path(key: &str) -> Option<std::path::PathBuf> {
+    Some(dirs::data_dir()?.join("ld54").join(format!("{key}.ron")))
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn load(key: &str) -> Option<String> {
+    local_storage()?.get_item(&format!("ld54.{key}")).ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn save(key: &str, contents: &str) -> Result<(), String> {
+    local_storage()
+        .ok_or("No localStorage")?
+        .set_item(&format!("ld54.{key}"), contents)
+        .map_err(|e| format!("Couldn't save {key}: {e:?}"))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+/// Seconds since the Unix epoch.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
+
+/// Seconds since the Unix epoch. `SystemTime` panics in the browser.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn now() -> u64 {
+    (js_sys::Date::now() / 1000.0) as u64
+}
@@ -0,0 +1,211 @@
+use bevy::{input::touch::TouchPhase, prelude::*};
+use leafwing_input_manager::{buttonlike::ButtonState, plugin::InputManagerSystem, prelude::*};
+
+use crate::player::Player;
+use crate::ui::node_rect;
+use crate::{Action, GameState, LDAssets};
+
+/// How big the on-screen buttons are, in logical pixels.
+const TOUCH_BUTTON_SIZE: f32 = 90.0;
+
+/// On-screen left, right and jump buttons for phones. They stay hidden until
+/// the first touch, so nobody on a desktop ever sees them.
+///
+/// Requires [`LDAssets`], [`GameState`] and the `Action` input manager
+/// plugin.
+pub(crate) struct TouchPlugin;
+
+impl Plugin for TouchPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TouchDetected>()
+            .add_systems(Update, detect_touch)
+            .add_systems(OnEnter(GameState::Playing), touch_overlay_setup)
+            .add_systems(OnExit(GameState::Playing), remove_touch_overlay)
+            .add_systems(
+                Update,
+                show_touch_overlay.run_if(resource_changed::<TouchDetected>()),
+            )
+            // Straight after leafwing has read the keyboard and gamepads, so
+            // everything in Update sees the touches too.
+            .add_systems(
+                PreUpdate,
+                touch_inputs
+                    .after(InputManagerSystem::Update)
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+/// Whether anyone has touched the screen yet.
+#[derive(Resource, Default)]
+pub(crate) struct TouchDetected(pub(crate) bool);
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+enum TouchButton {
+    Left,
+    Right,
+    Jump,
+}
+
+#[derive(Component)]
+struct TouchOverlay;
+
+fn detect_touch(mut touches: EventReader<TouchInput>, mut detected: ResMut<TouchDetected>) {
+    if !detected.0
+        && touches
+            .iter()
+            .any(|touch| touch.phase == TouchPhase::Started)
+    {
+        detected.0 = true;
+    }
+}
+
+fn touch_overlay_setup(
+    assets: Res<LDAssets>,
+    detected: Res<TouchDetected>,
+    mut commands: Commands,
+) {
+    let visibility = if detected.0 {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+    let button = |button: TouchButton, left: Option<f32>, right: Option<f32>| {
+        (
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: left.map_or(Val::Auto, Val::Px),
+                    right: right.map_or(Val::Auto, Val::Px),
+                    // Clear of the HUD along the bottom.
+                    bottom: Val::Px(45.0),
+                    width: Val::Px(TOUCH_BUTTON_SIZE),
+                    height: Val::Px(TOUCH_BUTTON_SIZE),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: Color::rgba(1.0, 1.0, 1.0, 0.2).into(),
+                visibility,
+                ..default()
+            },
+            button,
+            TouchOverlay,
+        )
+    };
+    let label = |text: &str| {
+        TextBundle::from_section(
+            text,
+            TextStyle {
+                font: assets.font.clone(),
+                font_size: 50.0,
+                color: Color::rgba(1.0, 1.0, 1.0, 0.6),
+            },
+        )
+    };
+
+    commands
+        .spawn(button(TouchButton::Left, Some(10.0), None))
+        .with_children(|parent| {
+            parent.spawn(label("<"));
+        });
+    commands
+        .spawn(button(
+            TouchButton::Right,
+            Some(20.0 + TOUCH_BUTTON_SIZE),
+            None,
+        ))
+        .with_children(|parent| {
+            parent.spawn(label(">"));
+        });
+    commands
+        .spawn(button(TouchButton::Jump, None, Some(10.0)))
+        .with_children(|parent| {
+            parent.spawn(label("^"));
+        });
+}
+
+fn remove_touch_overlay(mut commands: Commands, overlay: Query<Entity, With<TouchOverlay>>) {
+    for entity in &overlay {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn show_touch_overlay(
+    detected: Res<TouchDetected>,
+    mut overlay: Query<&mut Visibility, With<TouchOverlay>>,
+) {
+    if !detected.0 {
+        return;
+    }
+    for mut visibility in &mut overlay {
+        *visibility = Visibility::Inherited;
+    }
+}
+
+/// Presses the player's actions for every button with a finger on it. Each
+/// finger is checked on its own, so you can hold right and tap jump.
+fn touch_inputs(
+    touches: Res<Touches>,
+    detected: Res<TouchDetected>,
+    buttons: Query<(&TouchButton, &Node, &GlobalTransform)>,
+    mut player: Query<&mut ActionState<Action>, With<Player>>,
+) {
+    if !detected.0 {
+        return;
+    }
+    let Ok(mut action_state) = player.get_single_mut() else {
+        return;
+    };
+
+    let held = |wanted: TouchButton, just: bool| {
+        buttons
+            .iter()
+            .filter(|(button, _, _)| **button == wanted)
+            .any(|(_, node, transform)| {
+                let rect = node_rect(node, transform);
+                let mut fingers = touches
+                    .iter()
+                    .filter(|touch| rect.contains(touch.position()));
+                if just {
+                    fingers.any(|touch| touches.just_pressed(touch.id()))
+                } else {
+                    fingers.next().is_some()
+                }
+            })
+    };
+
+    let direction = match (
+        held(TouchButton::Left, false),
+        held(TouchButton::Right, false),
+    ) {
+        (true, false) => -1.0,
+        (false, true) => 1.0,
+        _ => 0.0,
+    };
+    if direction != 0.0 {
+        press_and_hold(&mut action_state, Action::Move);
+        action_state.action_data_mut(Action::Move).value = direction;
+    }
+
+    if held(TouchButton::Jump, true) {
+        action_state.press(Action::Jump);
+    } else if held(TouchButton::Jump, false) {
+        press_and_hold(&mut action_state, Action::Jump);
+    }
+}
+
+/// Leafwing releases anything with no key or button behind it at the start of
+/// every frame. Pressing it again would make it look freshly pressed every
+/// frame, so a held finger sets it straight to held.
+fn press_and_hold(action_state: &mut ActionState<Action>, action: Action) {
+    if action_state.pressed(action) {
+        return;
+    }
+    let data = action_state.action_data_mut(action);
+    data.state = if data.state == ButtonState::JustReleased {
+        ButtonState::Pressed
+    } else {
+        ButtonState::JustPressed
+    };
+}
@@ -0,0 +1,508 @@
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use crate::campaign::Campaign;
+use crate::difficulty::Difficulty;
+use crate::level::Level;
+use crate::lives::Lives;
+use crate::scores::{HighScores, ScoreEntry};
+use crate::settings::SettingsState;
+use crate::{
+    gameplay_running, remove_all_sprites, storage, GameMode, GameState, LDAssets, Randomizer,
+};
+
+/// Splash, level complete and game over screens plus the survival timer HUD.
+/// Solo runs that end in a game over go on the [`HighScores`] table.
+///
+/// Requires [`LDAssets`], [`Randomizer`], [`Campaign`], [`Difficulty`],
+/// [`GameMode`], [`Lives`], [`HighScores`] and [`GameState`].
+pub(crate) struct UiPlugin;
+
+impl Plugin for UiPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SurvivalTime>()
+            .add_systems(
+                Update,
+                wait_to_start
+                    .run_if(
+                        in_state(GameState::Splash)
+                            .or_else(in_state(GameState::LevelComplete))
+                            .or_else(in_state(GameState::GameOver)),
+                    )
+                    .run_if(in_state(SettingsState::Closed)),
+            )
+            .add_systems(OnEnter(GameState::Splash), splash_setup)
+            .add_systems(
+                Update,
+                (choose_difficulty, choose_mode, open_settings)
+                    .run_if(in_state(GameState::Splash).and_then(in_state(SettingsState::Closed))),
+            )
+            .add_systems(
+                OnEnter(GameState::LevelComplete),
+                (level_complete_setup, bank_level_time),
+            )
+            .add_systems(OnExit(GameState::LevelComplete), remove_all_text)
+            .add_systems(
+                OnEnter(GameState::GameOver),
+                (record_score, gg_setup).chain(),
+            )
+            .add_systems(OnEnter(GameState::Playing), hud_setup)
+            .add_systems(
+                OnExit(GameState::Splash),
+                (remove_all_sprites, remove_all_text, reset_run_time),
+            )
+            .add_systems(
+                OnExit(GameState::GameOver),
+                (remove_all_sprites, remove_all_text, reset_run_time),
+            )
+            .add_systems(OnExit(GameState::Playing), remove_all_text)
+            .add_systems(
+                Update,
+                (
+                    update_timer,
+                    update_lives.run_if(resource_changed::<Lives>()),
+                )
+                    .run_if(gameplay_running),
+            );
+    }
+}
+
+/// The keyboard, every gamepad and the touch screen, for menus that take any
+/// of them.
+#[derive(SystemParam)]
+pub(crate) struct MenuInput<'w> {
+    pub(crate) keys: Res<'w, Input<KeyCode>>,
+    pub(crate) buttons: Res<'w, Input<GamepadButton>>,
+    gamepads: Res<'w, Gamepads>,
+    touches: Res<'w, Touches>,
+}
+
+impl MenuInput<'_> {
+    pub(crate) fn pressed(&self, key: KeyCode, button: GamepadButtonType) -> bool {
+        self.keys.just_pressed(key)
+            || self
+                .gamepads
+                .iter()
+                .any(|gamepad| self.buttons.just_pressed(GamepadButton::new(gamepad, button)))
+    }
+
+    /// Space, enter or the south button.
+    pub(crate) fn confirmed(&self) -> bool {
+        self.pressed(KeyCode::Space, GamepadButtonType::South)
+            || self.keys.just_pressed(KeyCode::Return)
+    }
+
+    /// Where a finger just came down on `node`, if one did.
+    pub(crate) fn tap_on(&self, node: &Node, transform: &GlobalTransform) -> Option<Vec2> {
+        let rect = node_rect(node, transform);
+        self.touches
+            .iter_just_pressed()
+            .map(Touch::position)
+            .find(|&position| rect.contains(position))
+    }
+
+    pub(crate) fn any_tap(&self) -> bool {
+        self.touches.any_just_pressed()
+    }
+}
+
+/// Where a UI node is on screen, in the same logical pixels as touches.
+pub(crate) fn node_rect(node: &Node, transform: &GlobalTransform) -> Rect {
+    Rect::from_center_size(transform.translation().truncate(), node.size())
+}
+
+/// A full screen column for a menu's rows, darkening whatever's behind it.
+pub(crate) fn menu_overlay(darkness: f32, z_index: ZIndex) -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            row_gap: Val::Px(10.0),
+            ..default()
+        },
+        background_color: Color::rgba(0.0, 0.0, 0.0, darkness).into(),
+        z_index,
+        ..default()
+    }
+}
+
+/// Tears down a menu, or anything else tagged with `T`, and its children.
+pub(crate) fn despawn_all<T: Component>(mut commands: Commands, things: Query<Entity, With<T>>) {
+    for thing in &things {
+        commands.entity(thing).despawn_recursive();
+    }
+}
+
+#[derive(Component)]
+struct DifficultyLabel;
+
+#[derive(Component)]
+struct ModeLabel;
+
+fn mode_text(mode: GameMode) -> &'static str {
+    match mode {
+        GameMode::Solo => "Solo (V for versus)",
+        GameMode::Versus => "Versus (V for solo)",
+    }
+}
+
+fn difficulty_text(difficulty: Difficulty) -> String {
+    format!("< {} >", difficulty.name())
+}
+
+fn splash_setup(
+    assets: Res<LDAssets>,
+    difficulty: Res<Difficulty>,
+    mode: Res<GameMode>,
+    mut commands: Commands,
+) {
+    commands.spawn(SpriteBundle {
+        texture: assets.splash.clone(),
+        ..default()
+    });
+    let text_style = TextStyle {
+        font: assets.font.clone(),
+        font_size: 30.0,
+        color: Color::WHITE,
+    };
+    commands.spawn((
+        TextBundle::from_sections([
+            TextSection::new("Difficulty: ", text_style.clone()),
+            TextSection::new(difficulty_text(*difficulty), text_style.clone()),
+        ])
+        .with_text_alignment(TextAlignment::Center)
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(5.0),
+            right: Val::Px(15.0),
+            ..default()
+        }),
+        DifficultyLabel,
+    ));
+    commands.spawn((
+        TextBundle::from_sections([
+            TextSection::new("Mode: ", text_style.clone()),
+            TextSection::new(mode_text(*mode), text_style.clone()),
+        ])
+        .with_text_alignment(TextAlignment::Center)
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(40.0),
+            right: Val::Px(15.0),
+            ..default()
+        }),
+        ModeLabel,
+    ));
+    commands.spawn(
+        TextBundle::from_section("O for settings", text_style).with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(75.0),
+            right: Val::Px(15.0),
+            ..default()
+        }),
+    );
+}
+
+fn open_settings(k: Res<Input<KeyCode>>, mut next_state: ResMut<NextState<SettingsState>>) {
+    if k.just_pressed(KeyCode::O) {
+        next_state.set(SettingsState::Open);
+    }
+}
+
+/// V on the splash screen switches between solo and versus.
+fn choose_mode(
+    k: Res<Input<KeyCode>>,
+    mut mode: ResMut<GameMode>,
+    mut label: Query<&mut Text, With<ModeLabel>>,
+) {
+    if !k.just_pressed(KeyCode::V) {
+        return;
+    }
+    *mode = match *mode {
+        GameMode::Solo => GameMode::Versus,
+        GameMode::Versus => GameMode::Solo,
+    };
+    for mut text in &mut label {
+        text.sections[1].value = mode_text(*mode).to_owned();
+    }
+}
+
+/// Left and right on the splash screen pick the difficulty.
+fn choose_difficulty(
+    k: Res<Input<KeyCode>>,
+    mut difficulty: ResMut<Difficulty>,
+    mut label: Query<&mut Text, With<DifficultyLabel>>,
+) {
+    let chosen = if k.just_pressed(KeyCode::Left) {
+        difficulty.step(false)
+    } else if k.just_pressed(KeyCode::Right) {
+        difficulty.step(true)
+    } else {
+        return;
+    };
+    *difficulty = chosen;
+    for mut text in &mut label {
+        text.sections[1].value = difficulty_text(chosen);
+    }
+}
+
+/// Versus runs don't count, the paddle player decides how long they last.
+fn record_score(
+    survival_time: Res<SurvivalTime>,
+    rng: Res<Randomizer>,
+    difficulty: Res<Difficulty>,
+    mode: Res<GameMode>,
+    mut scores: ResMut<HighScores>,
+) {
+    if *mode == GameMode::Versus {
+        scores.latest = None;
+        return;
+    }
+    scores.record(ScoreEntry {
+        time: survival_time.run(),
+        timestamp: storage::now(),
+        seed: rng.seed,
+        difficulty: *difficulty,
+    });
+}
+
+fn gg_setup(
+    assets: Res<LDAssets>,
+    rng: Res<Randomizer>,
+    scores: Res<HighScores>,
+    mut commands: Commands,
+) {
+    commands.spawn(SpriteBundle {
+        texture: assets.gameover.clone(),
+        ..default()
+    });
+    let text_style = TextStyle {
+        font: assets.font.clone(),
+        font_size: 60.0,
+        color: Color::BLACK,
+    };
+    // TODO: Put this in the middle of the screen and blink.
+    commands.spawn(
+        TextBundle::from_section("Press space to restart", text_style)
+            .with_text_alignment(TextAlignment::Center)
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(5.0),
+                right: Val::Px(15.0),
+                ..default()
+            }),
+    );
+    // Testers attach this to bug reports so the run can be replayed.
+    commands.spawn(
+        TextBundle::from_section(
+            format!("Seed: {}", rng.seed),
+            TextStyle {
+                font: assets.font.clone(),
+                font_size: 20.0,
+                color: Color::BLACK,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(5.0),
+            left: Val::Px(5.0),
+            ..default()
+        }),
+    );
+
+    let title = if scores.is_new_record() {
+        "New record!"
+    } else {
+        "High scores"
+    };
+    let rows = scores.entries.iter().enumerate().map(|(i, entry)| {
+        (
+            format!(
+                "{:>2}. {:>8.2} s   {}   {:<6}   seed {}",
+                i + 1,
+                entry.time,
+                entry.date(),
+                entry.difficulty.name(),
+                entry.seed
+            ),
+            scores.latest == Some(i),
+        )
+    });
+    for (row, (text, highlight)) in std::iter::once((title.to_owned(), scores.is_new_record()))
+        .chain(rows)
+        .enumerate()
+    {
+        commands.spawn(
+            TextBundle::from_section(
+                text,
+                TextStyle {
+                    font: assets.font.clone(),
+                    font_size: 20.0,
+                    color: if highlight { Color::RED } else { Color::BLACK },
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(40.0 + 22.0 * row as f32),
+                left: Val::Px(5.0),
+                ..default()
+            }),
+        );
+    }
+}
+
+fn level_complete_setup(
+    assets: Res<LDAssets>,
+    levels: Res<Assets<Level>>,
+    campaign: Res<Campaign>,
+    mut commands: Commands,
+) {
+    let name = campaign
+        .current(&levels)
+        .map(|level| level.name.clone())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| format!("Level {}", campaign.level + 1));
+    let (title, prompt) = if campaign.is_last_level() {
+        (
+            "The gods relent. Your home is safe.".to_owned(),
+            "Press space to play again",
+        )
+    } else {
+        (format!("{name} endured!"), "Press space for the next level")
+    };
+
+    for (text, font_size, top) in [(title.as_str(), 50.0, 35.0), (prompt, 30.0, 55.0)] {
+        commands.spawn(
+            TextBundle::from_section(
+                text,
+                TextStyle {
+                    font: assets.font.clone(),
+                    font_size,
+                    color: Color::WHITE,
+                },
+            )
+            .with_text_alignment(TextAlignment::Center)
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                top: Val::Percent(top),
+                width: Val::Percent(100.0),
+                ..default()
+            }),
+        );
+    }
+}
+
+fn remove_all_text(mut commands: Commands, things_to_remove: Query<Entity, With<Text>>) {
+    for thing_to_remove in &things_to_remove {
+        let mut entity_commands = commands.entity(thing_to_remove);
+        entity_commands.despawn();
+    }
+}
+
+/// Space, or a tap for phones.
+fn wait_to_start(
+    k: Res<Input<KeyCode>>,
+    touches: Res<Touches>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if k.just_pressed(KeyCode::Space) || touches.any_just_pressed() {
+        next_state.set(GameState::Playing);
+    }
+}
+
+/// Seconds survived. Kept after the level ends so the game over screen can
+/// put the run on the high score table.
+#[derive(Resource, Default)]
+struct SurvivalTime {
+    /// On the current level, shown in the HUD.
+    level: f32,
+    /// On the levels already cleared this run.
+    cleared: f32,
+}
+
+impl SurvivalTime {
+    /// The whole run so far, which is what goes on the high score table.
+    fn run(&self) -> f32 {
+        self.cleared + self.level
+    }
+}
+
+/// Banks the level just cleared into the run.
+fn bank_level_time(mut survival_time: ResMut<SurvivalTime>) {
+    survival_time.cleared += survival_time.level;
+    survival_time.level = 0.0;
+}
+
+/// A new run starts when the splash or game over screen is left.
+fn reset_run_time(mut survival_time: ResMut<SurvivalTime>) {
+    *survival_time = SurvivalTime::default();
+}
+
+#[derive(Component)]
+struct SurvivalTimeText;
+
+fn hud_setup(
+    assets: Res<LDAssets>,
+    mut survival_time: ResMut<SurvivalTime>,
+    mut commands: Commands,
+) {
+    // Restarting a level throws away the time spent on it.
+    survival_time.level = 0.0;
+    let text_style = TextStyle {
+        font: assets.font.clone(),
+        font_size: 30.0,
+        color: Color::WHITE,
+    };
+    commands.spawn((
+        TextBundle::from_sections([
+            TextSection::new("Gods' wrath endured for: ", text_style.clone()),
+            TextSection::from_style(text_style.clone()),
+        ])
+        .with_text_alignment(TextAlignment::Left)
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(5.0),
+            left: Val::Px(5.0),
+            ..default()
+        }),
+        SurvivalTimeText,
+    ));
+    commands.spawn((
+        TextBundle::from_sections([
+            TextSection::new("Lives: ", text_style.clone()),
+            TextSection::from_style(text_style),
+        ])
+        .with_text_alignment(TextAlignment::Right)
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(5.0),
+            right: Val::Px(15.0),
+            ..default()
+        }),
+        LivesLeft,
+    ));
+}
+
+#[derive(Component)]
+struct LivesLeft;
+
+fn update_lives(lives: Res<Lives>, mut text_widget: Query<&mut Text, With<LivesLeft>>) {
+    for mut text in &mut text_widget {
+        text.sections[1].value = lives.left.to_string();
+    }
+}
+
+fn update_timer(
+    time: Res<Time>,
+    mut survival_time: ResMut<SurvivalTime>,
+    mut text_widget: Query<&mut Text, With<SurvivalTimeText>>,
+) {
+    survival_time.level += time.delta_seconds();
+    let Ok(mut text) = text_widget.get_single_mut() else {
+        return;
+    };
+    text.sections[1].value = format!("{:.2} s", survival_time.level);
+}